@@ -0,0 +1,439 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+const CONFIG_FILE: &str = "csm.toml";
+
+/// Project-wide settings.
+///
+/// They are read from the first of these that exists:
+/// - a `csm.toml` file next to the crate's Cargo.toml
+/// - the `[package.metadata.csm]` table of the crate's Cargo.toml
+/// - a `csm.toml` file at the root of the workspace
+/// - the `[workspace.metadata.csm]` table of the workspace's Cargo.toml
+///
+/// The `CSM_OUT_DIR` and `CSM_MINIFY` environment variables take precedence over them.
+#[derive(Debug)]
+pub struct Config {
+    /// Where the CSS files and the bundle are written, in a `debug` or `release` subdirectory
+    /// of the configured one, `target/csm` by default.
+    pub out_dir: PathBuf,
+    /// The URL the bundle is served from, prepended to its file name in `href`s.
+    pub public_path: String,
+    /// Whether the bundle is minified.
+    pub minify: bool,
+    /// Browserslist queries for the browsers the bundle must support, vendor prefixes are added
+    /// and modern syntax is lowered for them. Empty when no specific browsers are targeted.
+    pub browserslist: Vec<String>,
+    /// Breakpoints sorted by ascending width, so that later ones win in the bundle.
+    pub breakpoints: Vec<Breakpoint>,
+    /// How the classes generated by the macros are named.
+    pub class_names: ClassNames,
+    /// Prefixes used in class names instead of the full name of a property, e.g. `bg` for
+    /// `background-color`.
+    pub abbreviations: BTreeMap<String, String>,
+    /// Whether `csm!` checks that the variables it references are defined by `csm_defs!`,
+    /// which can be turned off when variables are also defined in other stylesheets.
+    pub check_variables: bool,
+    /// What `csm!` does with the values lightningcss doesn't understand, which are either
+    /// invalid or use CSS it doesn't support yet, e.g. `anchor()`.
+    pub check_values: CheckValues,
+    /// The file the settings were read from, if any, so that the macros can be expanded again
+    /// when it changes.
+    pub source: Option<PathBuf>,
+}
+
+/// The naming scheme of the generated classes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClassNames {
+    /// Names spelling out the declaration, e.g. `hover:bg_$gray` or `w_calc(100%_-_2rem)`.
+    Readable,
+    /// Short names derived from a hash of the readable name, e.g. `k3x9fa`.
+    Hashed,
+}
+
+/// How `csm!` reports the values lightningcss doesn't understand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckValues {
+    /// They are rejected, `check-values = true`.
+    Error,
+    /// They only get a warning, `check-values = "warn"`.
+    Warn,
+    /// They are accepted, `check-values = false`.
+    Off,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigFile {
+    out_dir: Option<PathBuf>,
+    public_path: Option<String>,
+    minify: Option<bool>,
+    browserslist: Option<Queries>,
+    breakpoints: Option<BTreeMap<String, String>>,
+    class_names: Option<ClassNames>,
+    abbreviations: Option<BTreeMap<String, String>>,
+    check_variables: Option<bool>,
+    check_values: Option<CheckValuesSetting>,
+}
+
+/// Either `true` or `false`, or the name of a [`CheckValues`], e.g. `"warn"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CheckValuesSetting {
+    Enabled(bool),
+    Level(CheckValues),
+}
+
+/// Either a single browserslist query, e.g. `"> 0.5%, last 2 versions"`, or a list of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Queries {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let manifest_dir = env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+        let workspace_dir = workspace_dir(&manifest_dir);
+
+        let (file, source) = match find_config_file(&manifest_dir, &workspace_dir)? {
+            Some((file, source)) => (file, Some(source)),
+            None => (ConfigFile::default(), None),
+        };
+        let config_dir = match source.as_ref().and_then(|source| source.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => manifest_dir.clone(),
+        };
+
+        // relative paths are resolved from the file they are declared in
+        let out_dir = match (env::var_os("CSM_OUT_DIR"), file.out_dir) {
+            (Some(dir), _) => workspace_dir.join(dir),
+            (None, Some(dir)) => config_dir.join(dir),
+            (None, None) => default_out_dir(&workspace_dir),
+        };
+        let out_dir = out_dir.join(profile());
+
+        let minify = match env::var("CSM_MINIFY") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| format!("invalid CSM_MINIFY `{}`, expected true or false", value))?,
+            Err(_) => file.minify.unwrap_or(default_minify()),
+        };
+
+        let mut breakpoints = match file.breakpoints {
+            Some(breakpoints) => breakpoints
+                .into_iter()
+                .map(|(name, width)| Breakpoint::new(name, width))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => default_breakpoints(),
+        };
+        breakpoints.sort_by(|a, b| a.px.total_cmp(&b.px));
+
+        // configured abbreviations are added to the default ones, or override them
+        let mut abbreviations = default_abbreviations();
+        abbreviations.extend(file.abbreviations.unwrap_or_default());
+        check_abbreviations(&abbreviations)?;
+
+        Ok(Config {
+            out_dir,
+            public_path: file.public_path.unwrap_or_else(|| "/".to_string()),
+            minify,
+            browserslist: match file.browserslist {
+                Some(Queries::One(query)) => vec![query],
+                Some(Queries::Many(queries)) => queries,
+                None => vec![],
+            },
+            breakpoints,
+            class_names: file.class_names.unwrap_or(default_class_names()),
+            abbreviations,
+            check_variables: file.check_variables.unwrap_or(true),
+            check_values: match file.check_values {
+                Some(CheckValuesSetting::Enabled(true)) | None => CheckValues::Error,
+                Some(CheckValuesSetting::Enabled(false)) => CheckValues::Off,
+                Some(CheckValuesSetting::Level(level)) => level,
+            },
+            source,
+        })
+    }
+
+    /// The URL of the bundle with the given hash, as it should appear in
+    /// `<link rel="stylesheet">`.
+    pub fn bundle_href(&self, hash: &str) -> String {
+        format!("{}{}", self.public_path, crate::hashed_file_name(hash))
+    }
+
+    pub fn breakpoint(&self, name: &str) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|bp| bp.name == name)
+    }
+
+    /// The name of `prop` in class names: its abbreviation, or the property itself.
+    ///
+    /// Fails when a property without abbreviation is named like the abbreviation of another
+    /// one, as their classes could clash.
+    pub fn prop_ident<'a>(&'a self, prop: &'a str) -> Result<&'a str, String> {
+        if let Some(abbreviation) = self.abbreviations.get(prop) {
+            return Ok(abbreviation);
+        }
+        match self.abbreviations.iter().find(|(_, a)| *a == prop) {
+            Some((other, _)) => Err(format!(
+                "property `{}` clashes with the abbreviation of `{}`, add an abbreviation for it",
+                prop, other
+            )),
+            None => Ok(prop),
+        }
+    }
+
+    /// Position of the breakpoint in the bundle, unknown breakpoints go last.
+    pub fn breakpoint_rank(&self, name: &str) -> usize {
+        self.breakpoints
+            .iter()
+            .position(|bp| bp.name == name)
+            .unwrap_or(self.breakpoints.len())
+    }
+}
+
+/// Returns the first config found, along with the file it was found in.
+fn find_config_file(
+    manifest_dir: &Path,
+    workspace_dir: &Path,
+) -> Result<Option<(ConfigFile, PathBuf)>, String> {
+    let candidates = [
+        (manifest_dir, None),
+        (manifest_dir, Some("package")),
+        (workspace_dir, None),
+        (workspace_dir, Some("workspace")),
+    ];
+    for (dir, metadata_of) in candidates {
+        let path = match metadata_of {
+            None => dir.join(CONFIG_FILE),
+            Some(_) => dir.join("Cargo.toml"),
+        };
+        let config = match metadata_of {
+            None => read_config_file(&path)?,
+            Some(table) => read_cargo_metadata(&path, table)?,
+        };
+        if let Some(config) = config {
+            return Ok(Some((config, path)));
+        }
+    }
+    Ok(None)
+}
+
+fn read_config_file(path: &Path) -> Result<Option<ConfigFile>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    toml::from_str::<ConfigFile>(&contents)
+        .map(Some)
+        .map_err(|e| format!("invalid {}: {}", path.display(), e))
+}
+
+/// Reads the `[<table>.metadata.csm]` table of a Cargo.toml.
+fn read_cargo_metadata(path: &Path, table: &str) -> Result<Option<ConfigFile>, String> {
+    let Some(manifest) = read_manifest(path) else {
+        return Ok(None);
+    };
+    let metadata = manifest
+        .get(table)
+        .and_then(|t| t.get("metadata"))
+        .and_then(|m| m.get("csm"));
+    match metadata {
+        Some(metadata) => metadata
+            .clone()
+            .try_into::<ConfigFile>()
+            .map(Some)
+            .map_err(|e| {
+                format!(
+                    "invalid [{}.metadata.csm] in {}: {}",
+                    table,
+                    path.display(),
+                    e
+                )
+            }),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn read_manifest(path: &Path) -> Option<toml::Table> {
+    let contents = fs::read_to_string(path).ok()?;
+    toml::from_str::<toml::Table>(&contents).ok()
+}
+
+/// The nearest directory, starting from the crate's one, whose Cargo.toml declares a
+/// `[workspace]`, like cargo does, or the crate's directory when it isn't part of one.
+fn workspace_dir(manifest_dir: &Path) -> PathBuf {
+    manifest_dir
+        .ancestors()
+        .find(|dir| {
+            read_manifest(&dir.join("Cargo.toml"))
+                .is_some_and(|manifest| manifest.contains_key("workspace"))
+        })
+        .unwrap_or(manifest_dir)
+        .to_path_buf()
+}
+
+/// Release builds are minified, as well as any build with the `minify` feature enabled.
+fn default_minify() -> bool {
+    cfg!(feature = "minify") || !cfg!(debug_assertions)
+}
+
+/// Release builds get short class names, debug builds readable ones.
+fn default_class_names() -> ClassNames {
+    if cfg!(debug_assertions) {
+        ClassNames::Readable
+    } else {
+        ClassNames::Hashed
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// A `csm` directory inside the target directory used by cargo.
+///
+/// Every crate of the workspace must end up writing to the same directory, so it is derived
+/// from the workspace rather than from the `OUT_DIR` of the crate being compiled, which also
+/// depends on `--target` and only exists for crates with a build script.
+fn default_out_dir(workspace_dir: &Path) -> PathBuf {
+    let target_dir = match env::var_os("CARGO_TARGET_DIR") {
+        Some(target_dir) => workspace_dir.join(target_dir),
+        None => {
+            configured_target_dir(workspace_dir).unwrap_or_else(|| workspace_dir.join("target"))
+        }
+    };
+    target_dir.join("csm")
+}
+
+/// Debug and release builds name classes differently, so they get a directory each: a release
+/// build would otherwise overwrite the CSS of the crates a debug build doesn't recompile.
+fn profile() -> &'static str {
+    if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    }
+}
+
+/// The `build.target-dir` of the first `.cargo/config.toml` declaring one, looking from the
+/// workspace up to the root, then in the cargo home. Relative paths are resolved from the
+/// directory containing `.cargo`.
+fn configured_target_dir(workspace_dir: &Path) -> Option<PathBuf> {
+    let cargo_home = env::var_os("CARGO_HOME").map(PathBuf::from);
+    let config_dirs = workspace_dir
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(cargo_home);
+    for config_dir in config_dirs {
+        for name in ["config.toml", "config"] {
+            let target_dir = read_manifest(&config_dir.join(name)).and_then(|config| {
+                let target_dir = config.get("build")?.get("target-dir")?.as_str()?;
+                Some(config_dir.parent()?.join(target_dir))
+            });
+            if target_dir.is_some() {
+                return target_dir;
+            }
+        }
+    }
+    None
+}
+
+fn default_breakpoints() -> Vec<Breakpoint> {
+    [("sm", 640.), ("md", 768.), ("lg", 1024.), ("xl", 1280.)]
+        .into_iter()
+        .map(|(name, px)| Breakpoint {
+            name: name.to_string(),
+            min_width: format!("{}px", px),
+            px,
+        })
+        .collect()
+}
+
+fn default_abbreviations() -> BTreeMap<String, String> {
+    [
+        ("display", "d"),
+        ("align-items", "items"),
+        ("justify-content", "justify"),
+        ("height", "h"),
+        ("width", "w"),
+        ("background-color", "bg"),
+        ("border-radius", "rounded"),
+        ("font-size", "fs"),
+        ("padding", "p"),
+    ]
+    .into_iter()
+    .map(|(prop, abbreviation)| (prop.to_string(), abbreviation.to_string()))
+    .collect()
+}
+
+/// Makes sure that no two properties share the same abbreviation.
+fn check_abbreviations(abbreviations: &BTreeMap<String, String>) -> Result<(), String> {
+    let mut props = BTreeMap::new();
+    for (prop, abbreviation) in abbreviations {
+        if abbreviation.is_empty() {
+            return Err(format!("empty abbreviation for `{}`", prop));
+        }
+        if let Some(other) = props.insert(abbreviation, prop) {
+            return Err(format!(
+                "`{}` and `{}` have the same abbreviation `{}`",
+                other, prop, abbreviation
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A named `min-width` media query, usable as `md: { ... }` inside `csm!`.
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    pub name: String,
+    pub min_width: String,
+    px: f64,
+}
+
+impl Breakpoint {
+    fn new(name: String, min_width: String) -> Result<Self, String> {
+        let split = min_width
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(min_width.len());
+        let (value, unit) = min_width.split_at(split);
+        let value = value
+            .parse::<f64>()
+            .map_err(|_| format!("invalid width `{}` for breakpoint `{}`", min_width, name))?;
+        let px = match unit {
+            "px" => value,
+            "em" | "rem" => value * 16.,
+            _ => {
+                return Err(format!(
+                    "invalid width `{}` for breakpoint `{}`, expected a length in px, em or rem",
+                    min_width, name
+                ))
+            }
+        };
+        Ok(Breakpoint {
+            name,
+            min_width,
+            px,
+        })
+    }
+
+    pub fn media_query(&self) -> String {
+        format!("(min-width: {})", self.min_width)
+    }
+}
@@ -0,0 +1,231 @@
+//! Assembles the CSS written by the `csm!` macros into a single bundle.
+//!
+//! The macros only write a small CSS file for each of their invocations, this crate is meant to
+//! be called once, after the crates using csm have been compiled, to produce `bundle.css`:
+//!
+//! ```no_run
+//! let files = csm_build::bundle().expect("failed to bundle CSS");
+//! println!("CSS bundle written to {}", files.css.display());
+//! ```
+//!
+//! It can be called from a post-build step, or from the `main` of a development server. Not
+//! from a `build.rs` though: cargo doesn't wait for the dependencies of a crate to be compiled
+//! before running its build script, nor runs it again when they change. The `include_bundle!`
+//! and `bundle_href!` macros bundle at compile time instead.
+mod config;
+#[doc(hidden)]
+pub mod manifest;
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use lightningcss::{
+    bundler::{Bundler, FileProvider},
+    stylesheet::{MinifyOptions, ParserOptions, PrinterOptions},
+    targets::{Browsers, Targets},
+};
+
+pub use crate::config::{Breakpoint, CheckValues, ClassNames, Config};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration couldn't be loaded.
+    Config(String),
+    /// The CSS files couldn't be read, or the bundle couldn't be written.
+    Io(io::Error),
+    /// The CSS files couldn't be bundled together.
+    Css(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "invalid configuration: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Css(e) => write!(f, "failed to bundle CSS: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// The crates whose CSS goes in a bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Every crate compiled with the same output directory.
+    All,
+    /// The crate being compiled and its dependencies, for the macros bundling at compile time.
+    CurrentCrate,
+}
+
+/// The files written by [`write_bundle`].
+#[derive(Clone, Debug)]
+pub struct BundleFiles {
+    /// `bundle.css`, always at the same path.
+    pub css: PathBuf,
+    /// `bundle.<hash>.css`, with the same content, to be served with long-lived cache headers.
+    pub hashed_css: PathBuf,
+    /// The hash in the name of `hashed_css`.
+    pub hash: String,
+}
+
+/// Loads the configuration of the current crate and writes the bundle in its output directory.
+pub fn bundle() -> Result<BundleFiles, Error> {
+    let config = Config::load().map_err(Error::Config)?;
+    write_bundle(&config, Scope::All)
+}
+
+/// Writes `bundle.css`, `bundle.<hash>.css` and `bundle.json` in the output directory of
+/// `config`. `bundle.json` maps `bundle.css` to the name of the hashed file.
+pub fn write_bundle(config: &Config, scope: Scope) -> Result<BundleFiles, Error> {
+    let css = bundle_css(config, scope)?;
+    let hash = content_hash(&css);
+    let hashed_name = hashed_file_name(&hash);
+
+    // the binaries compiled earlier keep referencing their own hashed bundle, the others are
+    // replaced by the latest one
+    let referenced = manifest::bundle_hashes(&config.out_dir);
+    for file in fs::read_dir(&config.out_dir)?.filter_map(|file| file.ok()) {
+        let name = file.file_name().to_string_lossy().into_owned();
+        let hash = name
+            .strip_prefix("bundle.")
+            .and_then(|name| name.strip_suffix(".css"));
+        let is_hashed =
+            hash.is_some_and(|h| h.len() == 8 && h.chars().all(|c| c.is_ascii_hexdigit()));
+        if is_hashed && name != hashed_name && !hash.is_some_and(|h| referenced.contains(h)) {
+            // another build might have removed it already
+            let _ = fs::remove_file(file.path());
+        }
+    }
+
+    let files = BundleFiles {
+        css: config.out_dir.join("bundle.css"),
+        hashed_css: config.out_dir.join(&hashed_name),
+        hash,
+    };
+    write(&files.css, &css)?;
+    write(&files.hashed_css, &css)?;
+    write(
+        &config.out_dir.join("bundle.json"),
+        &format!("{{\"bundle.css\": \"{}\"}}\n", hashed_name),
+    )?;
+
+    Ok(files)
+}
+
+/// Bundles the CSS files of the crates in `scope`, deduplicating and minifying the rules.
+pub fn bundle_css(config: &Config, scope: Scope) -> Result<String, Error> {
+    fs::create_dir_all(&config.out_dir)?;
+    let abs_out = fs::canonicalize(&config.out_dir)?;
+
+    // several crates can be bundling at the same time
+    let tmp_path = abs_out.join(format!("bundle.{}.tmp.css", std::process::id()));
+    let mut bundle = fs::File::create(&tmp_path)?;
+    let mut files = manifest::collect_css_files(&abs_out, scope);
+    manifest::check_class_names(&abs_out, scope).map_err(Error::Css)?;
+    // rules scoped to a breakpoint must come after the unscoped ones, and larger breakpoints
+    // after smaller ones, so that they win when more than one applies
+    files.sort_by_cached_key(|file| {
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        let rank = match name.split_once('@') {
+            Some((_, breakpoint)) => config.breakpoint_rank(breakpoint) + 1,
+            None => 0,
+        };
+        (rank, file.clone())
+    });
+    for file in files {
+        bundle.write_fmt(format_args!("@import \"{}\";\n", file.display()))?;
+    }
+
+    let fp = FileProvider::new();
+    let res = Bundler::new(&fp, None, ParserOptions::default()).bundle(&tmp_path);
+    fs::remove_file(&tmp_path)?;
+    let mut res = res.map_err(|e| Error::Css(e.to_string()))?;
+    let targets = targets(config)?;
+    res.minify(MinifyOptions {
+        targets,
+        ..Default::default()
+    })
+    .map_err(|e| Error::Css(e.to_string()))?;
+    let css = res
+        .to_css(PrinterOptions {
+            minify: config.minify,
+            targets,
+            ..Default::default()
+        })
+        .map_err(|e| Error::Css(e.to_string()))?
+        .code;
+
+    Ok(css)
+}
+
+fn targets(config: &Config) -> Result<Targets, Error> {
+    if config.browserslist.is_empty() {
+        return Ok(Targets::default());
+    }
+    let browsers = Browsers::from_browserslist(&config.browserslist).map_err(|e| {
+        Error::Config(format!(
+            "invalid browserslist `{}`: {}",
+            config.browserslist.join(", "),
+            e
+        ))
+    })?;
+    Ok(Targets::from(browsers))
+}
+
+/// A short hash, stable across builds and Rust versions, used for cache busting and for hashed
+/// class names.
+pub fn content_hash(contents: &str) -> String {
+    // folded to 32 bits
+    let hash = fnv1a(contents);
+    format!("{:08x}", (hash >> 32) as u32 ^ hash as u32)
+}
+
+/// 64-bit FNV-1a.
+pub fn fnv1a(contents: &str) -> u64 {
+    contents.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The name of the bundle with the given hash.
+pub fn hashed_file_name(hash: &str) -> String {
+    format!("bundle.{}.css", hash)
+}
+
+/// Writes a file atomically: the contents go to a temporary file first, which is then renamed,
+/// so that the crates bundling at the same time never read a half-written file.
+#[doc(hidden)]
+pub fn write(path: &Path, contents: &str) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    // starts with a dot so that it isn't mistaken for a CSS file
+    let tmp_path = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::File::create(&tmp_path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    written.map_err(|e| path_error(path, e))
+}
+
+/// Adds the path to the message of an I/O error, which doesn't mention it.
+fn path_error(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
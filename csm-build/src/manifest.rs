@@ -0,0 +1,478 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Scope;
+
+const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Identifies the current compilation: rustc loads the proc-macro once per crate it compiles,
+/// so this is unique to the crate being compiled. This doesn't hold under rust-analyzer, see
+/// [`is_rust_analyzer`].
+fn session() -> &'static str {
+    static SESSION: OnceLock<String> = OnceLock::new();
+    SESSION.get_or_init(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        format!("{}-{}", std::process::id(), nanos)
+    })
+}
+
+/// Whether the macros are being expanded by rust-analyzer rather than by rustc.
+///
+/// Its proc-macro server stays loaded across crates and edits, and expands macros one at a
+/// time, so nothing must be recorded then: the ids it happens to expand would replace the ones
+/// recorded by the last build, and the next bundle would lose the CSS of the others.
+pub fn is_rust_analyzer() -> bool {
+    static IS_RUST_ANALYZER: OnceLock<bool> = OnceLock::new();
+    *IS_RUST_ANALYZER.get_or_init(|| {
+        env::var_os("RUST_ANALYZER_INTERNALS_DO_NOT_USE").is_some()
+            || env::current_exe().is_ok_and(|exe| {
+                exe.file_name()
+                    .is_some_and(|name| name.to_string_lossy().contains("rust-analyzer"))
+            })
+    })
+}
+
+/// Whether the crate being compiled is a test harness, e.g. the unit tests of a library or an
+/// integration test. These record nothing, as their CSS would otherwise leak into the bundle of
+/// the library, which has the same crate name, and cargo compiles both at the same time.
+pub fn is_test() -> bool {
+    static IS_TEST: OnceLock<bool> = OnceLock::new();
+    // proc-macros run inside rustc, and can't see `cfg(test)`
+    *IS_TEST.get_or_init(|| env::args().any(|arg| arg == "--test"))
+}
+
+/// The ids emitted by the last compilation of a crate. Each crate gets its own directory
+/// inside `css/`, containing the manifest and a CSS file per id (and breakpoint).
+///
+/// Each id also records its [`Entry`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Manifest {
+    session: String,
+    manifest_dir: PathBuf,
+    /// The package of the crate, and whether the crate is its library, which the crates
+    /// depending on the package see.
+    #[serde(default)]
+    package: String,
+    #[serde(default)]
+    lib: bool,
+    /// The packages the crate depends on, see [`dependencies`].
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    dependencies: BTreeSet<String>,
+    ids: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    classes: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    variables: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    origins: BTreeMap<String, String>,
+    /// The files the macros were called from, see [`Manifest::is_stale`].
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    files: BTreeSet<PathBuf>,
+    /// The hash of the bundle whose URL `bundle_href!` expanded to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bundle: Option<String>,
+}
+
+/// What an id emits besides its CSS files.
+#[derive(Debug, Default)]
+pub struct Entry {
+    /// Hashed class names along with the readable name they were derived from, so that
+    /// collisions can be detected when bundling.
+    pub classes: BTreeMap<String, String>,
+    /// CSS variables defined by `csm_defs!`, along with their value, so that references to
+    /// them can be checked.
+    pub variables: BTreeMap<String, String>,
+    /// Where the macro emitting the id was called, e.g. `src/lib.rs:3:1`, see
+    /// [`Manifest::other_origin`].
+    pub origin: String,
+    /// The absolute path of the file the macro was called from.
+    pub file: Option<PathBuf>,
+}
+
+/// Name of the directory for the crate being compiled. Binaries get a suffix, so that they
+/// don't clash with a library of the same package.
+fn crate_key() -> String {
+    let name = env::var("CARGO_CRATE_NAME")
+        .or_else(|_| env::var("CARGO_PKG_NAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    match env::var_os("CARGO_BIN_NAME") {
+        Some(_) => format!("{}-bin", name),
+        None => name,
+    }
+}
+
+/// Whether the crate being compiled is the library of its package, rather than a binary, an
+/// example or an integration test.
+fn is_lib() -> bool {
+    env::var_os("CARGO_BIN_NAME").is_none() && env::var_os("CARGO_TARGET_TMPDIR").is_none()
+}
+
+/// The packages the crate being compiled may depend on, read from its Cargo.toml: regular and
+/// dev-dependencies, including the platform-specific ones, by their package name.
+fn dependencies() -> &'static BTreeSet<String> {
+    static DEPENDENCIES: OnceLock<BTreeSet<String>> = OnceLock::new();
+    DEPENDENCIES.get_or_init(|| {
+        let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+        let Some(cargo_toml) =
+            crate::config::read_manifest(&Path::new(&manifest_dir).join("Cargo.toml"))
+        else {
+            return BTreeSet::new();
+        };
+        let targets = cargo_toml
+            .get("target")
+            .and_then(|targets| targets.as_table())
+            .into_iter()
+            .flat_map(|targets| targets.values())
+            .filter_map(|target| target.as_table());
+        std::iter::once(&cargo_toml)
+            .chain(targets)
+            .flat_map(|table| ["dependencies", "dev-dependencies"].map(|key| table.get(key)))
+            .flatten()
+            .filter_map(|dependencies| dependencies.as_table())
+            .flatten()
+            .map(|(name, dependency)| {
+                // renamed dependencies, e.g. `tokens = { package = "my-tokens" }`
+                dependency
+                    .get("package")
+                    .and_then(|package| package.as_str())
+                    .unwrap_or(name)
+                    .to_string()
+            })
+            .collect()
+    })
+}
+
+impl Manifest {
+    /// The values of `map` for the ids of the last compilation.
+    fn entries<'a>(
+        &'a self,
+        map: &'a BTreeMap<String, BTreeMap<String, String>>,
+    ) -> impl Iterator<Item = (&'a String, &'a String)> {
+        map.iter()
+            .filter(|(id, _)| self.ids.contains(*id))
+            .flat_map(|(_, entries)| entries)
+    }
+
+    fn read(crate_dir: &Path) -> Option<Manifest> {
+        let contents = fs::read_to_string(crate_dir.join(MANIFEST_FILE)).ok()?;
+        toml::from_str(&contents).ok()
+    }
+
+    fn write(&self, crate_dir: &Path) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        crate::write(&crate_dir.join(MANIFEST_FILE), &contents)
+    }
+
+    /// Whether the crate changed since its last compilation recorded this manifest: one of the
+    /// files the macros were called from was modified or removed. The crate will be compiled
+    /// again, and record its ids again if it still calls the macros, but until then its CSS
+    /// might be outdated, or not used at all, e.g. after its last `csm!` was removed.
+    fn is_stale(&self, crate_dir: &Path) -> bool {
+        if self.session == session() {
+            return false;
+        }
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+        let Ok(written) = modified(&crate_dir.join(MANIFEST_FILE)) else {
+            return false;
+        };
+        self.files
+            .iter()
+            .any(|file| modified(file).map_or(true, |modified| modified > written))
+    }
+
+    /// Records that `id` was emitted by the current compilation of the crate, along with its
+    /// entry, and returns the directory its CSS files belong to, or `None` when nothing must be
+    /// written because the macros are expanded by rust-analyzer, or for a test harness.
+    ///
+    /// The first time this is called in a compilation, the ids recorded by the previous one are
+    /// forgotten, so that the ones that disappeared from the code get pruned.
+    pub fn record(out_dir: &Path, id: &str, entry: Entry) -> io::Result<Option<PathBuf>> {
+        if is_rust_analyzer() || is_test() {
+            return Ok(None);
+        }
+        let crate_dir = out_dir.join("css").join(crate_key());
+        let mut manifest = Manifest::current(&crate_dir)?;
+        manifest.ids.insert(id.to_string());
+        insert_or_remove(&mut manifest.classes, id, entry.classes);
+        insert_or_remove(&mut manifest.variables, id, entry.variables);
+        if entry.origin.is_empty() {
+            manifest.origins.remove(id);
+        } else {
+            manifest.origins.insert(id.to_string(), entry.origin);
+        }
+        manifest.files.extend(entry.file);
+        manifest.write(&crate_dir)?;
+
+        Ok(Some(crate_dir))
+    }
+
+    /// Records that the current compilation of the crate references the bundle with the given
+    /// hash, which must then be kept around, see [`bundle_hashes`].
+    pub fn record_bundle(out_dir: &Path, hash: &str, file: Option<PathBuf>) -> io::Result<()> {
+        if is_rust_analyzer() || is_test() {
+            return Ok(());
+        }
+        let crate_dir = out_dir.join("css").join(crate_key());
+        let mut manifest = Manifest::current(&crate_dir)?;
+        manifest.bundle = Some(hash.to_string());
+        manifest.files.extend(file);
+        manifest.write(&crate_dir)
+    }
+
+    /// The manifest of the current compilation of the crate, which is empty when nothing was
+    /// recorded yet.
+    fn current(crate_dir: &Path) -> io::Result<Manifest> {
+        fs::create_dir_all(crate_dir).map_err(|e| crate::path_error(crate_dir, e))?;
+        Ok(match Manifest::read(crate_dir) {
+            Some(manifest) if manifest.session == session() => manifest,
+            _ => Manifest {
+                session: session().to_string(),
+                manifest_dir: env::var_os("CARGO_MANIFEST_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_default(),
+                package: env::var("CARGO_PKG_NAME").unwrap_or_default(),
+                lib: is_lib(),
+                dependencies: dependencies().clone(),
+                ids: BTreeSet::new(),
+                classes: BTreeMap::new(),
+                variables: BTreeMap::new(),
+                origins: BTreeMap::new(),
+                files: BTreeSet::new(),
+                bundle: None,
+            },
+        })
+    }
+
+    /// Where another macro call of the current compilation of the crate emitted `id`, if any
+    /// did: the CSS of the call at `origin` would replace its own.
+    pub fn other_origin(out_dir: &Path, id: &str, origin: &str) -> Option<String> {
+        let manifest = Manifest::read(&out_dir.join("css").join(crate_key()))?;
+        if manifest.session != session() || !manifest.ids.contains(id) {
+            return None;
+        }
+        manifest
+            .origins
+            .get(id)
+            .filter(|other| !origin.is_empty() && *other != origin)
+            .cloned()
+    }
+}
+
+/// Returns the CSS files that belong to the last compilation of each crate in `scope`, deleting
+/// the rest: files of ids that are not in their crate's manifest anymore, and directories of
+/// crates that don't exist anymore or are stale, see [`Manifest::is_stale`], whatever the scope.
+///
+/// Several crates can be compiled, and bundled, at the same time: failing to delete a file is
+/// fine, as another build might have deleted it already.
+pub fn collect_css_files(out_dir: &Path, scope: Scope) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let Ok(entries) = fs::read_dir(out_dir.join("css")) else {
+        return files;
+    };
+    let in_scope = scope_filter(out_dir, scope);
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            // left over by older versions, which didn't group files by crate
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+
+        // the crate might be writing its first file right now
+        let Some(manifest) = Manifest::read(&path) else {
+            continue;
+        };
+        if !manifest.manifest_dir.join("Cargo.toml").exists() || manifest.is_stale(&path) {
+            let _ = fs::remove_dir_all(&path);
+            continue;
+        }
+        let crate_key = path.file_name().unwrap_or_default().to_string_lossy();
+        if !in_scope(&crate_key) {
+            continue;
+        }
+
+        let Ok(crate_files) = fs::read_dir(&path) else {
+            continue;
+        };
+        for file in crate_files
+            .filter_map(|file| file.ok())
+            .map(|file| file.path())
+        {
+            // files being written are renamed once complete, see `crate::write`
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if name == MANIFEST_FILE || name.starts_with('.') {
+                continue;
+            }
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            let id = stem.split_once('@').map_or(&*stem, |(id, _)| id);
+            if manifest.ids.contains(id) {
+                files.push(file);
+            } else {
+                let _ = fs::remove_file(&file);
+            }
+        }
+    }
+
+    files
+}
+
+/// Checks that no hashed class name was generated for two different rules, which is unlikely
+/// but possible since the hashes are kept short.
+pub fn check_class_names(out_dir: &Path, scope: Scope) -> Result<(), String> {
+    let in_scope = scope_filter(out_dir, scope);
+    let mut readable_names = BTreeMap::new();
+    for (_, manifest) in read_all(out_dir)
+        .into_iter()
+        .filter(|(crate_key, _)| in_scope(crate_key))
+    {
+        for (class, readable) in manifest.entries(&manifest.classes) {
+            match readable_names.insert(class.clone(), readable.clone()) {
+                Some(other) if other != *readable => {
+                    return Err(format!(
+                        "class name `{}` was generated for both `{}` and `{}`, use \
+                         `class-names = \"readable\"` or change one of the rules",
+                        class, other, readable
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A CSS variable defined by `csm_defs!`.
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    /// The id of the `csm_defs!` defining it.
+    pub id: String,
+    /// The directory of the crate defining it, inside `css/`.
+    pub crate_key: String,
+    /// Whether it comes from a previous compilation of the crate being compiled, in which case
+    /// it might have been removed since.
+    pub outdated: bool,
+    /// Whether it is defined by the crate being compiled or one of its dependencies, rather
+    /// than by an unrelated crate that happened to be compiled before.
+    pub visible: bool,
+}
+
+impl Variable {
+    /// Whether the variable is defined by `id` in the crate being compiled.
+    pub fn is_defined_by(&self, id: &str) -> bool {
+        self.crate_key == crate_key() && self.id == id
+    }
+}
+
+/// The variables defined by every crate, since they all end up in the same bundle.
+pub fn variables(out_dir: &Path) -> Vec<Variable> {
+    let current_crate = crate_key();
+    let manifests = read_all(out_dir);
+    let visible = visible_crates(&manifests);
+    let mut variables = vec![];
+    for (crate_key, manifest) in manifests {
+        let outdated = crate_key == current_crate && manifest.session != session();
+        let visible = visible.contains(&crate_key);
+        for (id, entries) in &manifest.variables {
+            if !manifest.ids.contains(id) {
+                continue;
+            }
+            variables.extend(entries.iter().map(|(name, value)| Variable {
+                name: name.clone(),
+                value: value.clone(),
+                id: id.clone(),
+                crate_key: crate_key.clone(),
+                outdated,
+                visible,
+            }));
+        }
+    }
+    variables
+}
+
+/// The crate being compiled and the libraries it depends on, directly or through other
+/// crates using csm. Binaries, examples and tests also see the library of their own package.
+fn visible_crates(manifests: &[(String, Manifest)]) -> BTreeSet<String> {
+    let mut visible = BTreeSet::from([crate_key()]);
+    let mut packages = dependencies().clone();
+    if !is_lib() {
+        packages.extend(env::var("CARGO_PKG_NAME"));
+    }
+    let mut pending = packages.clone();
+    while let Some(package) = pending.pop_first() {
+        for (crate_key, manifest) in manifests {
+            if manifest.lib && manifest.package == package && visible.insert(crate_key.clone()) {
+                for dependency in &manifest.dependencies {
+                    if packages.insert(dependency.clone()) {
+                        pending.insert(dependency.clone());
+                    }
+                }
+            }
+        }
+    }
+    visible
+}
+
+/// The hashes of the bundles referenced by the last compilation of each crate, through
+/// `bundle_href!`.
+pub fn bundle_hashes(out_dir: &Path) -> BTreeSet<String> {
+    read_all(out_dir)
+        .into_iter()
+        .filter_map(|(_, manifest)| manifest.bundle)
+        .collect()
+}
+
+/// Whether the CSS of a crate, given the name of its directory, belongs to `scope`.
+fn scope_filter(out_dir: &Path, scope: Scope) -> impl Fn(&str) -> bool {
+    let visible = match scope {
+        Scope::All => None,
+        Scope::CurrentCrate => Some(visible_crates(&read_all(out_dir))),
+    };
+    move |crate_key| {
+        visible
+            .as_ref()
+            .is_none_or(|visible| visible.contains(crate_key))
+    }
+}
+
+/// The manifests of the crates that still exist and aren't stale, along with the name of their
+/// directory.
+fn read_all(out_dir: &Path) -> Vec<(String, Manifest)> {
+    let Ok(entries) = fs::read_dir(out_dir.join("css")) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let crate_key = path.file_name()?.to_string_lossy().into_owned();
+            let manifest = Manifest::read(&path)?;
+            let exists = manifest.manifest_dir.join("Cargo.toml").exists();
+            (exists && !manifest.is_stale(&path)).then_some((crate_key, manifest))
+        })
+        .collect()
+}
+
+fn insert_or_remove(
+    map: &mut BTreeMap<String, BTreeMap<String, String>>,
+    id: &str,
+    entries: BTreeMap<String, String>,
+) {
+    if entries.is_empty() {
+        map.remove(id);
+    } else {
+        map.insert(id.to_string(), entries);
+    }
+}
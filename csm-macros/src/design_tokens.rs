@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use serde_json::Value as Json;
+use syn::parse::ParseStream;
+
+use crate::{property, write_defs, Context, TokenDef, TokenDefs};
+
+pub fn include_tokens_impl(input: TokenStream) -> TokenStream {
+    let cx = match Context::load_or_error() {
+        Ok(cx) => cx,
+        Err(e) => return e,
+    };
+    let include = match cx.parse(input, IncludeTokens::parse) {
+        Ok(include) => include,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let path = include.path();
+    let defs = match include.read_defs(&path) {
+        Ok(defs) => defs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let defs_tokens = TokenStream2::from(write_defs(&defs, &cx, include.file.span()));
+
+    // makes cargo rebuild the crate when the file changes
+    let path = path.to_string_lossy();
+    quote! {
+        #defs_tokens
+        const _: &[u8] = include_bytes!(#path);
+    }
+    .into()
+}
+
+/// `include_tokens!("tokens.json")`, or `include_tokens!(brand, "tokens.json")` to name the
+/// generated module like a named `csm_defs!`.
+struct IncludeTokens {
+    name: Option<syn::Ident>,
+    file: syn::LitStr,
+}
+
+impl IncludeTokens {
+    fn parse(input: ParseStream, _: &Context) -> syn::Result<Self> {
+        let name = if input.peek(syn::Ident) {
+            let name = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![,]>()?;
+            Some(name)
+        } else {
+            None
+        };
+        let file = input.parse::<syn::LitStr>()?;
+        if input.peek(syn::Token![,]) {
+            input.parse::<syn::Token![,]>()?;
+        }
+        Ok(IncludeTokens { name, file })
+    }
+
+    /// The path of the file, which is relative to the crate's Cargo.toml.
+    fn path(&self) -> PathBuf {
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        manifest_dir.join(self.file.value())
+    }
+
+    fn read_defs(&self, path: &Path) -> syn::Result<TokenDefs> {
+        let span = self.file.span();
+        let contents = fs::read_to_string(path).map_err(|e| {
+            syn::Error::new(
+                span,
+                format!("failed to read tokens: {}: {}", path.display(), e),
+            )
+        })?;
+        let json = serde_json::from_str::<Json>(&contents).map_err(|e| {
+            syn::Error::new(span, format!("invalid JSON in {}: {}", path.display(), e))
+        })?;
+
+        let mut tokens = BTreeMap::new();
+        collect_tokens(&json, &mut vec![], &mut tokens)
+            .map_err(|e| syn::Error::new(span, format!("{}: {}", path.display(), e)))?;
+
+        let mut defs = TokenDefs {
+            name: self.name.clone(),
+            file: Some(self.file.value()),
+            tokens: BTreeMap::new(),
+        };
+        for (name, (_, value)) in tokens {
+            defs.insert(TokenDef { name, value, span })?;
+        }
+        Ok(defs)
+    }
+}
+
+/// Walks the groups of a W3C Design Tokens file, where tokens are the objects with a `$value`,
+/// or a `value` for Style Dictionary files, and names them after their path, e.g. `color-red`
+/// for `{ "color": { "red": { "$value": "#f00" } } }`, along with their path.
+fn collect_tokens(
+    group: &Json,
+    path: &mut Vec<String>,
+    tokens: &mut BTreeMap<String, (String, String)>,
+) -> Result<(), String> {
+    let Json::Object(group) = group else {
+        return Err(format!(
+            "expected a group or a token at `{}`",
+            path.join(".")
+        ));
+    };
+    if let Some(value) = group.get("$value").or_else(|| group.get("value")) {
+        let name = path.join("-");
+        if name.is_empty() {
+            return Err("the root of the file can't be a token".to_string());
+        }
+        let token = path.join(".");
+        let value = css_value(value)
+            .and_then(|value| property::check_custom_value(&value).map(|_| value))
+            .map_err(|e| format!("token `{}`: {}", token, e))?;
+        // e.g. `{ "a-b": .. }` and `{ "a": { "b": .. } }`
+        if let Some((other, _)) = tokens.get(&name) {
+            return Err(format!(
+                "tokens `{}` and `{}` would both define the variable `{}`",
+                other, token, name
+            ));
+        }
+        tokens.insert(name, (token, value));
+        return Ok(());
+    }
+
+    for (key, child) in group {
+        // `$type`, `$description` and the like
+        if key.starts_with('$') {
+            continue;
+        }
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("`{}` can't be part of a variable name", key));
+        }
+        path.push(key.clone());
+        collect_tokens(child, path, tokens)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// The CSS for the value of a token. Composite values are supported when they fit in a single
+/// property, e.g. shadows or borders, but not typography.
+fn css_value(value: &Json) -> Result<String, String> {
+    match value {
+        Json::String(string) => Ok(resolve_references(string)),
+        Json::Number(number) => Ok(number.to_string()),
+        // cubic bezier
+        Json::Array(items) if items.len() == 4 && items.iter().all(|item| item.is_number()) => {
+            let items = items.iter().map(css_value).collect::<Result<Vec<_>, _>>()?;
+            Ok(format!("cubic-bezier({})", items.join(", ")))
+        }
+        // font families, or layered shadows
+        Json::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| match item {
+                    Json::String(family) if family.contains(' ') => Ok(format!(
+                        "\"{}\"",
+                        family.replace('\\', "\\\\").replace('"', "\\\"")
+                    )),
+                    item => css_value(item),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(items.join(", "))
+        }
+        Json::Object(object) => {
+            let field = |name: &str| object.get(name).map(css_value).transpose();
+            // dimensions and durations
+            if let (Some(value), Some(unit)) = (field("value")?, field("unit")?) {
+                return Ok(format!("{}{}", value, unit));
+            }
+            let parts = if object.contains_key("offsetX") {
+                let inset = match object.get("inset") {
+                    Some(Json::Bool(true)) => Some("inset".to_string()),
+                    _ => None,
+                };
+                vec![
+                    inset,
+                    field("offsetX")?,
+                    field("offsetY")?,
+                    field("blur")?,
+                    field("spread")?,
+                    field("color")?,
+                ]
+            } else if object.contains_key("style") && object.contains_key("width") {
+                vec![field("width")?, field("style")?, field("color")?]
+            } else if object.contains_key("duration") {
+                vec![
+                    field("duration")?,
+                    field("timingFunction")?,
+                    field("delay")?,
+                ]
+            } else {
+                return Err("unsupported composite value".to_string());
+            };
+            Ok(parts.into_iter().flatten().collect::<Vec<_>>().join(" "))
+        }
+        Json::Bool(_) | Json::Null => Err(format!("unsupported value `{}`", value)),
+    }
+}
+
+/// Turns references to other tokens, e.g. `{color.red}`, into references to their variable.
+fn resolve_references(value: &str) -> String {
+    let mut resolved = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let path = &rest[start + 1..start + end];
+        // Style Dictionary references point at the `value` of the token
+        let path = path.strip_suffix(".value").unwrap_or(path);
+        resolved.push_str(&rest[..start]);
+        resolved.push_str(&format!("var(--{})", path.replace('.', "-")));
+        rest = &rest[start + end + 1..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn collect(json: Json) -> Result<BTreeMap<String, (String, String)>, String> {
+        let mut tokens = BTreeMap::new();
+        collect_tokens(&json, &mut vec![], &mut tokens)?;
+        Ok(tokens)
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(css_value(&json!("#3b82f6")).unwrap(), "#3b82f6");
+        assert_eq!(css_value(&json!(1.5)).unwrap(), "1.5");
+        assert_eq!(
+            css_value(&json!({ "value": 4, "unit": "px" })).unwrap(),
+            "4px"
+        );
+        assert_eq!(
+            css_value(&json!([0.4, 0, 0.2, 1])).unwrap(),
+            "cubic-bezier(0.4, 0, 0.2, 1)"
+        );
+        assert_eq!(
+            css_value(&json!(["Inter", "Helvetica Neue", "sans-serif"])).unwrap(),
+            "Inter, \"Helvetica Neue\", sans-serif"
+        );
+        assert!(css_value(&json!({ "fontFamily": "Inter" })).is_err());
+        assert!(css_value(&json!(true)).is_err());
+    }
+
+    #[test]
+    fn shadows_and_borders() {
+        let shadow = json!({
+            "offsetX": "0px", "offsetY": "1px", "blur": "2px", "spread": "0px", "color": "#000",
+        });
+        assert_eq!(css_value(&shadow).unwrap(), "0px 1px 2px 0px #000");
+        let inset = json!({ "inset": true, "offsetX": "0", "offsetY": "1px", "color": "#000" });
+        assert_eq!(css_value(&inset).unwrap(), "inset 0 1px #000");
+        let layers =
+            json!([{ "offsetX": "0", "offsetY": "1px" }, { "offsetX": "0", "offsetY": "2px" }]);
+        assert_eq!(css_value(&layers).unwrap(), "0 1px, 0 2px");
+
+        let border = json!({ "width": "1px", "style": "solid", "color": "{color.gray}" });
+        assert_eq!(css_value(&border).unwrap(), "1px solid var(--color-gray)");
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(resolve_references("{color.red}"), "var(--color-red)");
+        // Style Dictionary
+        assert_eq!(resolve_references("{color.red.value}"), "var(--color-red)");
+        assert_eq!(
+            resolve_references("calc({size.base} * 2)"),
+            "calc(var(--size-base) * 2)"
+        );
+        assert_eq!(resolve_references("{unclosed"), "{unclosed");
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = collect(json!({
+            "color": {
+                "$type": "color",
+                "red": { "$value": "#f00" },
+                "danger": { "value": "{color.red.value}" },
+            },
+        }))
+        .unwrap();
+        let names = tokens
+            .iter()
+            .map(|(name, (path, value))| (&**name, &**path, &**value));
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            [
+                ("color-danger", "color.danger", "var(--color-red)"),
+                ("color-red", "color.red", "#f00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_tokens() {
+        let error = collect(json!({
+            "a-b": { "$value": "1px" },
+            "a": { "b": { "$value": "2px" } },
+        }))
+        .unwrap_err();
+        assert!(
+            error.contains("would both define the variable `a-b`"),
+            "{}",
+            error
+        );
+
+        let error = collect(json!({ "a": { "$value": "red; } body { color: blue" } })).unwrap_err();
+        assert!(error.contains("token `a`: invalid value"), "{}", error);
+        assert!(collect(json!({ "a": { "$value": "calc(1px" } })).is_err());
+        assert!(collect(json!({ "$value": "red" })).is_err());
+        assert!(collect(json!({ "a b": { "$value": "red" } })).is_err());
+    }
+}
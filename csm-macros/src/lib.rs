@@ -1,99 +1,277 @@
 extern crate proc_macro;
-use std::{collections::HashMap, fs, io::Write, path::Path};
+mod design_tokens;
+mod property;
+mod recipe;
+mod value;
 
-use lightningcss::{
-    bundler::{Bundler, FileProvider},
-    stylesheet::{MinifyOptions, ParserOptions, PrinterOptions},
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::BTreeMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
 };
+
 use proc_macro::TokenStream;
-use proc_macro2::Span;
-use quote::quote;
-use syn::{parse::Parse, parse_macro_input};
-
-const OUTPUT_DIR: &str = "./target/csm";
-
-fn write(path: &Path, def: &str) {
-    let mut f = fs::File::create(path).expect("failed to create file");
-    f.write(def.as_bytes()).expect("failed to write file");
-}
-
-fn write_bundle(path: &Path) {
-    let abs_out = std::fs::canonicalize(path).unwrap();
-
-    let mut bundle =
-        fs::File::create(abs_out.join("bundle.tmp.css")).expect("failed to create file");
-    let files = fs::read_dir(abs_out.join("css")).expect("failed to read dir");
-    for file in files.filter_map(|file| file.ok()) {
-        let modified = file
-            .metadata()
-            .expect("failed to read metadata")
-            .modified()
-            .expect("failed to read access time");
-        let is_old = modified
-            .elapsed()
-            .expect("failed to read elapsed time")
-            .gt(&std::time::Duration::from_secs(60 * 60 * 24));
-        if is_old {
-            eprintln!("WARN: {} not being used in a while, consider manually deleting it or it will end up in your bundle. If this is a mistake please open an issue on GitHub.", file.path().to_str().unwrap());
-        }
-
-        bundle
-            .write_fmt(format_args!(
-                "@import \"{}\";\n",
-                file.path().to_str().unwrap()
-            ))
-            .expect("failed to write file");
-    }
-
-    let fp = FileProvider::new();
-    let mut res = Bundler::new(&fp, None, ParserOptions::default())
-        .bundle(&abs_out.join("bundle.tmp.css"))
-        .expect("failed to bundle");
-    res.minify(MinifyOptions::default())
-        .expect("failed to minify");
-    let final_bundle = res
-        .to_css(PrinterOptions {
-            #[cfg(feature = "minify")]
-            minify: true,
-            ..Default::default()
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{
+    braced,
+    buffer::Cursor,
+    ext::IdentExt,
+    parse::{ParseStream, Parser},
+};
+
+use csm_build::{
+    fnv1a,
+    manifest::{self, Entry, Manifest},
+    write, Breakpoint, CheckValues, ClassNames, Config, Scope,
+};
+
+use crate::value::Value;
+
+/// Reports an error that isn't about a specific part of the input of the macro.
+fn call_site_error(message: impl fmt::Display) -> TokenStream {
+    syn::Error::new(Span::call_site(), message)
+        .to_compile_error()
+        .into()
+}
+
+/// Reports a file that couldn't be written as a compile error, rather than a panic.
+fn io_error(span: Span, e: io::Error) -> TokenStream {
+    syn::Error::new(span, format!("failed to write CSS: {}", e))
+        .to_compile_error()
+        .into()
+}
+
+/// Where the macro was called, e.g. `src/lib.rs:3:1`, or nothing when it isn't in a file.
+fn call_site_origin() -> String {
+    let span = proc_macro::Span::call_site();
+    match span.local_file() {
+        Some(file) => format!("{}:{}:{}", file.display(), span.line(), span.column()),
+        None => String::new(),
+    }
+}
+
+/// The absolute path of the file the macro was called from, which rustc gives relative to the
+/// directory it runs in.
+fn call_site_file() -> Option<PathBuf> {
+    let file = proc_macro::Span::call_site().local_file()?;
+    Some(match env::current_dir() {
+        Ok(dir) => dir.join(file),
+        Err(_) => file,
+    })
+}
+
+/// A short hash of where the macro was called, for the ids of macros named after an item,
+/// e.g. a recipe, as items with the same name can live in different modules.
+fn call_site_hash() -> String {
+    format!("{:08x}", fnv1a(&call_site_origin()) as u32)
+}
+
+/// Fails when another macro call of the crate already emitted `id`, as one of them would lose
+/// its CSS, with the error built by `message` from where that call is. Returns where this call
+/// is, to be recorded along with the id.
+fn unique_origin(
+    config: &Config,
+    id: &str,
+    span: Span,
+    message: impl FnOnce(&str) -> String,
+) -> syn::Result<String> {
+    let origin = call_site_origin();
+    match Manifest::other_origin(&config.out_dir, id, &origin) {
+        Some(other) => Err(syn::Error::new(span, message(&other))),
+        None => Ok(origin),
+    }
+}
+
+/// Makes cargo expand the macro again when the config file changes, as rustc tracks the files
+/// included by `include_bytes!`.
+///
+/// Only the macros expanding to items, and the bundle ones, track it: `csm!` must stay a string
+/// literal, so that it can be used with `concat!`. A crate is expanded again as a whole, so one
+/// of them is enough for its `csm!` calls to pick up the changes, but a crate with nothing but
+/// `csm!` calls is only expanded again once its own code changes.
+fn track_config(config: &Config) -> TokenStream2 {
+    match &config.source {
+        Some(source) => {
+            let source = source.to_string_lossy();
+            quote! { const _: &[u8] = include_bytes!(#source); }
+        }
+        None => TokenStream2::new(),
+    }
+}
+
+/// What parsing needs to know about the project, loaded once per macro invocation.
+struct Context {
+    config: Config,
+    /// The variables defined by `csm_defs!`, only read when they are needed.
+    variables: OnceCell<Vec<manifest::Variable>>,
+    /// Warnings to emit along with the expansion, see [`Context::warn`].
+    warnings: RefCell<Vec<TokenStream2>>,
+}
+
+impl Context {
+    fn load() -> Result<Self, String> {
+        Ok(Context {
+            config: Config::load()?,
+            variables: OnceCell::new(),
+            warnings: RefCell::default(),
         })
-        .expect("failed to print")
-        .code;
+    }
+
+    /// Loads the context, or returns the compile error the macro must expand to.
+    fn load_or_error() -> Result<Self, TokenStream> {
+        Context::load().map_err(call_site_error)
+    }
 
-    write(&abs_out.join("bundle.css"), &final_bundle);
+    fn variables(&self) -> &[manifest::Variable] {
+        self.variables
+            .get_or_init(|| manifest::variables(&self.config.out_dir))
+    }
+
+    /// The variables the crate being compiled can reference: the ones defined by itself and
+    /// its dependencies, whatever other crates were compiled before. The ones left by a previous
+    /// compilation of the crate don't count, so that incremental builds agree with clean ones.
+    fn visible_variables(&self) -> Vec<&manifest::Variable> {
+        self.variables()
+            .iter()
+            .filter(|variable| variable.visible && !variable.outdated)
+            .collect()
+    }
+
+    /// Reports a warning at `span`. Proc-macros can't emit warnings on stable Rust, so this
+    /// goes through the use of a deprecated item, which rustc warns about.
+    fn warn(&self, span: Span, message: &str) {
+        self.warnings.borrow_mut().push(quote_spanned! {span=>
+            const _: () = {
+                #[deprecated(note = #message)]
+                struct CsmWarning;
+                let _ = CsmWarning;
+            };
+        });
+    }
+
+    fn warnings(&self) -> TokenStream2 {
+        self.warnings.borrow().iter().cloned().collect()
+    }
 
-    //fs::remove_file(path.join("bundle.tmp.css")).expect("failed to remove file");
+    /// Parses the input of the macro with `parse`, which gets the context.
+    fn parse<T>(
+        &self,
+        tokens: TokenStream,
+        parse: fn(ParseStream, &Context) -> syn::Result<T>,
+    ) -> syn::Result<T> {
+        (|input: ParseStream| parse(input, self)).parse(tokens)
+    }
 }
 
 #[proc_macro]
 pub fn csm(tokens: TokenStream) -> TokenStream {
-    let csm = parse_macro_input!(tokens as Csm);
+    let cx = match Context::load_or_error() {
+        Ok(cx) => cx,
+        Err(e) => return e,
+    };
+    let csm = match cx.parse(tokens, Csm::parse) {
+        Ok(csm) => csm,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let config = &cx.config;
 
-    // write file
-    let out_dir = Path::new(OUTPUT_DIR);
-    fs::create_dir_all(out_dir.join("css")).expect("failed to create dir");
-    let out_file = out_dir.join("css").join(format!("{}.css", csm.id));
-    let css = csm
-        .rules
-        .0
-        .iter()
-        .map(|(_, rule)| rule.to_css_class())
-        .collect::<Vec<_>>()
-        .join("\n");
-    write(Path::new(&out_file), &css);
-
-    write_bundle(out_dir);
+    let id = csm.id.to_string();
+    let origin = match unique_origin(config, &id, csm.id.span(), |other| {
+        format!(
+            "`csm! {{ {}, .. }}` is already used at {}, ids must be unique in a crate",
+            id, other
+        )
+    }) {
+        Ok(origin) => origin,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let out_dir = config.out_dir.as_path();
+    if let Err(e) = write_rules(out_dir, &id, &csm.rules.0, config, origin) {
+        return io_error(csm.id.span(), e);
+    }
 
     // output list of classes
-    let classes = csm
-        .rules
-        .0
+    let classes = csm.rules.class_names(config.class_names);
+
+    // referencing the token constants makes sure they exist
+    let paths = csm.rules.token_paths();
+    let warnings = cx.warnings();
+    if paths.is_empty() && warnings.is_empty() {
+        quote! { #classes }.into()
+    } else {
+        quote! {{
+            #warnings
+            #(const _: ::csm::Token = #paths;)*
+            #classes
+        }}
+        .into()
+    }
+}
+
+/// Writes the CSS files for `id`, one for the unscoped rules and one for each breakpoint.
+fn write_rules(
+    out_dir: &Path,
+    id: &str,
+    rules: &[Rule],
+    config: &Config,
+    origin: String,
+) -> io::Result<()> {
+    let classes = rules
         .iter()
-        .map(|(_, rule)| rule.class_name())
-        .collect::<Vec<_>>()
-        .join(" ");
+        .map(|rule| {
+            (
+                rule.class_name(config.class_names),
+                rule.readable_class_name(),
+            )
+        })
+        .filter(|(class, readable)| class != readable)
+        .collect();
+    let entry = Entry {
+        classes,
+        origin,
+        file: call_site_file(),
+        ..Default::default()
+    };
+    let Some(crate_dir) = Manifest::record(out_dir, id, entry)? else {
+        return Ok(());
+    };
+    write_breakpoint_rules(&crate_dir, id, None, rules, config.class_names)?;
+    for breakpoint in &config.breakpoints {
+        write_breakpoint_rules(&crate_dir, id, Some(breakpoint), rules, config.class_names)?;
+    }
+    Ok(())
+}
 
-    quote! { #classes }.into()
+/// Writes the rules scoped to `breakpoint` to their own file, removing it if there are none.
+fn write_breakpoint_rules(
+    crate_dir: &Path,
+    id: &str,
+    breakpoint: Option<&Breakpoint>,
+    rules: &[Rule],
+    class_names: ClassNames,
+) -> io::Result<()> {
+    let out_file = match breakpoint {
+        Some(breakpoint) => crate_dir.join(format!("{}@{}.css", id, breakpoint.name)),
+        None => crate_dir.join(format!("{}.css", id)),
+    };
+    let css = rules
+        .iter()
+        .filter(|rule| rule.breakpoint.as_ref().map(|bp| &bp.name) == breakpoint.map(|bp| &bp.name))
+        .map(|rule| rule.to_css_class(class_names))
+        .collect::<Vec<_>>();
+
+    if css.is_empty() && breakpoint.is_some() {
+        return match fs::remove_file(&out_file) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {}", out_file.display(), e),
+            )),
+            _ => Ok(()),
+        };
+    }
+    write(&out_file, &css.join("\n"))
 }
 
 struct Csm {
@@ -101,51 +279,153 @@ struct Csm {
     rules: Rules,
 }
 
-impl Parse for Csm {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+impl Csm {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
         let id = input.parse::<syn::Ident>()?;
         input.parse::<syn::Token![,]>()?;
-        let rules = input.parse::<Rules>()?;
+        let rules = Rules::parse(input, cx)?;
         Ok(Csm { id, rules })
     }
 }
 
-#[derive(Clone, Debug)]
-struct Rules(HashMap<String, Rule>);
+/// Rules in the order they were declared.
+#[derive(Clone, Debug, Default)]
+struct Rules(Vec<Rule>);
+
+impl Rules {
+    fn insert(&mut self, rule: Rule, span: Span) -> syn::Result<()> {
+        if self.0.iter().any(|r| r.key() == rule.key()) {
+            return Err(syn::Error::new(
+                span,
+                format!("duplicate rule for property `{}`", rule.prop),
+            ));
+        }
+        self.0.push(rule);
+        Ok(())
+    }
+
+    /// Returns the rules of `self` overridden by the ones of `other` for the same property.
+    fn merge(&self, other: &Rules) -> Rules {
+        let mut rules = self.0.clone();
+        for rule in &other.0 {
+            match rules.iter_mut().find(|r| r.key() == rule.key()) {
+                Some(existing) => *existing = rule.clone(),
+                None => rules.push(rule.clone()),
+            }
+        }
+        Rules(rules)
+    }
+
+    fn token_paths(&self) -> Vec<&TokenStream2> {
+        self.0
+            .iter()
+            .flat_map(|rule| rule.value.token_paths())
+            .collect()
+    }
+
+    fn class_names(&self, class_names: ClassNames) -> String {
+        self.0
+            .iter()
+            .map(|rule| rule.class_name(class_names))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
 
-impl Parse for Rules {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut rules = HashMap::new();
+impl Rules {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let mut rules = Rules::default();
         while !input.is_empty() {
-            let rule = input.parse::<Rule>()?;
-            let name = rule.prop.clone();
-            match rules.insert(name, rule) {
-                Some(dup_rule) => {
-                    return Err(
-                        input.error(format!("duplicate rule for property `{}`", dup_rule.prop,))
-                    );
+            let span = input.span();
+            if is_nested_block(input) {
+                let name = parse_name(input)?;
+                input.parse::<syn::Token![:]>()?;
+                let body;
+                braced!(body in input);
+                let nested = Rules::parse(&body, cx)?;
+                if input.peek(syn::Token![,]) {
+                    input.parse::<syn::Token![,]>()?;
                 }
-                None => {}
+
+                if let Some(breakpoint) = cx.config.breakpoint(&name) {
+                    for mut rule in nested.0 {
+                        if rule.breakpoint.is_some() {
+                            return Err(syn::Error::new(
+                                span,
+                                "breakpoint blocks cannot be nested into each other",
+                            ));
+                        }
+                        rule.breakpoint = Some(breakpoint.clone());
+                        rules.insert(rule, span)?;
+                    }
+                } else {
+                    let pseudo =
+                        Pseudo::new(name, &cx.config).map_err(|e| syn::Error::new(span, e))?;
+                    for mut rule in nested.0 {
+                        rule.pseudos.insert(0, pseudo.clone());
+                        rules.insert(rule, span)?;
+                    }
+                }
+            } else {
+                rules.insert(Rule::parse(input, cx)?, span)?;
             }
         }
-        Ok(Rules(rules))
+        Ok(rules)
     }
 }
 
-// impl Rules {
-//     fn merge(&self, other: &Rules) -> Rules {
-//         let mut rules = self.0.clone();
-//         for (_, rule) in &other.0 {
-//             rules.insert(rule.prop.clone(), rule.clone());
-//         }
-//         Rules(rules)
-//     }
-// }
+/// Checks whether the input starts with a nested block such as `hover: { ... }` or
+/// `md: { ... }`.
+fn is_nested_block(input: ParseStream) -> bool {
+    let fork = input.fork();
+    parse_name(&fork).is_ok()
+        && fork.parse::<syn::Token![:]>().is_ok()
+        && fork.peek(syn::token::Brace)
+}
+
+/// Parses a name made of identifiers and dashes, such as `background-color` or
+/// `focus-visible`, stopping at the first `:`. Numbers can follow a dash, as in `spacing-4`.
+fn parse_name(input: ParseStream) -> syn::Result<String> {
+    let mut name = String::new();
+    while !input.is_empty() && !input.peek(syn::Token![:]) {
+        if let Ok(value) = input.call(syn::Ident::parse_any) {
+            // `r#type` is only raw for Rust
+            name.push_str(value.unraw().to_string().as_str());
+        } else if input.parse::<syn::Token![-]>().is_ok() {
+            name.push('-');
+        } else if name.ends_with('-') && input.peek(syn::LitInt) {
+            name.push_str(&input.parse::<syn::LitInt>()?.to_string());
+        } else {
+            return Err(input.error("error parsing name, expected ident or `-`"));
+        }
+    }
+    Ok(name)
+}
+
+/// The tokens consumed from `input` since `begin`, so that errors can point at all of them.
+fn tokens_since(begin: Cursor, input: ParseStream) -> TokenStream2 {
+    let end = input.cursor();
+    let mut tokens = TokenStream2::new();
+    let mut cursor = begin;
+    while cursor < end {
+        let Some((token, next)) = cursor.token_tree() else {
+            break;
+        };
+        tokens.extend([token]);
+        cursor = next;
+    }
+    tokens
+}
 
 #[derive(Clone, Debug)]
 struct Rule {
     prop: String,
-    values: Vec<Value>,
+    /// The property as it appears in class names, see [`Config::prop_ident`].
+    prop_ident: String,
+    value: Value,
+    important: bool,
+    pseudos: Vec<Pseudo>,
+    breakpoint: Option<Breakpoint>,
 }
 
 impl Rule {
@@ -153,133 +433,340 @@ impl Rule {
         let mut css = String::new();
         css.push_str(self.prop.as_str());
         css.push_str(": ");
-        css.push_str(
-            self.values
-                .iter()
-                .map(|v| v.value.clone())
-                .collect::<Vec<_>>()
-                .join(" ")
-                .as_str(),
-        );
-        css.push_str(";");
+        css.push_str(&self.value.to_css());
+        if self.important {
+            css.push_str(" !important");
+        }
+        css.push(';');
         css
     }
 
-    fn to_css_class(&self) -> String {
+    fn to_css_class(&self, class_names: ClassNames) -> String {
         let mut css = String::new();
 
-        css.push_str(".");
-        css.push_str(&self.class_name());
+        css.push('.');
+        css.push_str(&escape_ident(&self.class_name(class_names)));
+        for pseudo in &self.pseudos {
+            css.push_str(&pseudo.selector());
+        }
         css.push_str(" { ");
         css.push_str(&self.to_css());
         css.push_str(" }");
-        css
+
+        match &self.breakpoint {
+            Some(breakpoint) => format!("@media {} {{ {} }}", breakpoint.media_query(), css),
+            None => css,
+        }
+    }
+
+    /// Identifies the rule inside a [`Rules`] set: the same property can appear once per
+    /// combination of breakpoint and pseudo-classes.
+    fn key(&self) -> String {
+        let mut key = String::new();
+        if let Some(breakpoint) = &self.breakpoint {
+            key.push('@');
+            key.push_str(&breakpoint.name);
+        }
+        for pseudo in &self.pseudos {
+            key.push_str(&pseudo.selector());
+        }
+        key.push_str(&self.prop);
+        key
+    }
+
+    fn class_name(&self, class_names: ClassNames) -> String {
+        match class_names {
+            ClassNames::Readable => self.readable_class_name(),
+            ClassNames::Hashed => hashed_class_name(&self.readable_class_name()),
+        }
     }
 
-    fn class_name(&self) -> String {
+    /// A class name spelling out the rule, e.g. `md:hover:bg_$gray` or `color_red!`.
+    ///
+    /// Breakpoints and pseudo-classes are followed by a `:`, the property is separated from
+    /// its value by a `_`, spaces in the value become `_` too, and `!` marks important rules.
+    /// These characters are escaped with a `\` when they appear anywhere else, so that two
+    /// different rules never end up with the same class name.
+    fn readable_class_name(&self) -> String {
         let mut class_name = String::new();
-        class_name.push_str(&self.prop_ident());
-        class_name.push_str("_");
-        class_name.push_str(
-            &self
-                .values
-                .iter()
-                .map(|v| v.key.clone())
-                .collect::<Vec<_>>()
-                .join("_"),
-        );
+        if let Some(breakpoint) = &self.breakpoint {
+            class_name.push_str(&escape_class_part(&breakpoint.name));
+            class_name.push(':');
+        }
+        for pseudo in &self.pseudos {
+            class_name.push_str(&escape_class_part(&pseudo.name));
+            class_name.push(':');
+        }
+        class_name.push_str(&escape_class_part(&self.prop_ident));
+        class_name.push('_');
+        class_name.push_str(&escape_class_part(&self.value.to_class_text()));
+        if self.important {
+            class_name.push('!');
+        }
         class_name
     }
+}
 
-    fn prop_ident(&self) -> String {
-        match self.prop.as_str() {
-            "display" => "d".to_string(),
-            "align-items" => "items".to_string(),
-            "justify-content" => "justify".to_string(),
-            "height" => "h".to_string(),
-            "width" => "w".to_string(),
-            "background-color" => "bg".to_string(),
-            "border-radius" => "rounded".to_string(),
-            "font-size" => "fs".to_string(),
-            "padding" => "p".to_string(),
-            _ => self.prop.replace("-", "_"),
+impl Rule {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let begin = input.cursor();
+        let prop = parse_name(input)?;
+        let prop_tokens = tokens_since(begin, input);
+        input.parse::<syn::Token![:]>()?;
+        property::check_name(&prop).map_err(|e| syn::Error::new_spanned(&prop_tokens, e))?;
+        let prop_ident = cx
+            .config
+            .prop_ident(&prop)
+            .map_err(|e| syn::Error::new_spanned(&prop_tokens, e))?
+            .to_string();
+
+        let begin = input.cursor();
+        let value = Value::parse_declaration(input, cx)?;
+        let value_tokens = tokens_since(begin, input);
+        let unparsed = property::check_value(&prop, &value.to_css())
+            .map_err(|e| syn::Error::new_spanned(&value_tokens, e))?;
+        if let Some(message) = unparsed {
+            match cx.config.check_values {
+                CheckValues::Error => return Err(syn::Error::new_spanned(&value_tokens, message)),
+                CheckValues::Warn => {
+                    let span = value_tokens.into_iter().next().map(|t| t.span());
+                    cx.warn(span.unwrap_or_else(Span::call_site), &message);
+                }
+                CheckValues::Off => {}
+            }
+        }
+        if cx.config.check_variables {
+            check_variables(&value, cx)?;
+        }
+        let important = input.peek(syn::Token![!]);
+        if important {
+            input.parse::<syn::Token![!]>()?;
+            let ident = input.parse::<syn::Ident>()?;
+            if ident != "important" {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `important` after `!`",
+                ));
+            }
         }
+        if input.peek(syn::Token![,]) {
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        Ok(Rule {
+            prop,
+            prop_ident,
+            value,
+            important,
+            pseudos: vec![],
+            breakpoint: None,
+        })
     }
 }
 
-impl Parse for Rule {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut prop = String::new();
+/// Checks that the variables referenced by `value` are defined by a `csm_defs!` of the crate or
+/// its dependencies, if they have any: variables may otherwise be defined outside of csm.
+fn check_variables(value: &Value, cx: &Context) -> syn::Result<()> {
+    let references = value.variables();
+    if references.is_empty() {
+        return Ok(());
+    }
+    let defined = cx.visible_variables();
+    if defined.is_empty() {
+        return Ok(());
+    }
+    for (name, tokens) in references {
+        if defined.iter().any(|variable| variable.name == name) {
+            continue;
+        }
+        return Err(syn::Error::new_spanned(
+            tokens,
+            undefined_variable(name, &defined),
+        ));
+    }
+    Ok(())
+}
 
-        while !input.is_empty() && !input.peek(syn::Token![:]) {
-            if let Ok(value) = input.parse::<syn::Ident>() {
-                prop.push_str(value.to_string().as_str());
-            } else if let Ok(_) = input.parse::<syn::Token![-]>() {
-                prop.push_str("-");
-            } else {
-                return Err(input.error(format!(
-                    "error parsing property name, expected ident or `-`",
-                )));
-            }
+fn undefined_variable(name: &str, defined: &[&manifest::Variable]) -> String {
+    let names = defined.iter().map(|variable| variable.name.as_str());
+    match property::closest(name, names) {
+        Some(suggestion) => format!(
+            "undefined variable `${}`, did you mean `${}`?",
+            name, suggestion
+        ),
+        None => format!("undefined variable `${}`", name),
+    }
+}
+
+/// A pseudo-class (`:hover`) or pseudo-element (`::before`) the rule is scoped to.
+#[derive(Clone, Debug)]
+struct Pseudo {
+    name: String,
+}
+
+/// Pseudo-classes that take no argument, the only ones a nested block can be named after.
+const PSEUDO_CLASSES: &[&str] = &[
+    "active",
+    "any-link",
+    "autofill",
+    "blank",
+    "checked",
+    "current",
+    "default",
+    "defined",
+    "disabled",
+    "empty",
+    "enabled",
+    "first",
+    "first-child",
+    "first-of-type",
+    "focus",
+    "focus-visible",
+    "focus-within",
+    "fullscreen",
+    "future",
+    "hover",
+    "in-range",
+    "indeterminate",
+    "invalid",
+    "last-child",
+    "last-of-type",
+    "left",
+    "link",
+    "local-link",
+    "modal",
+    "only-child",
+    "only-of-type",
+    "open",
+    "optional",
+    "out-of-range",
+    "past",
+    "paused",
+    "picture-in-picture",
+    "placeholder-shown",
+    "playing",
+    "popover-open",
+    "read-only",
+    "read-write",
+    "required",
+    "right",
+    "root",
+    "scope",
+    "target",
+    "target-within",
+    "user-invalid",
+    "user-valid",
+    "valid",
+    "visited",
+];
+
+const PSEUDO_ELEMENTS: &[&str] = &[
+    "after",
+    "backdrop",
+    "before",
+    "cue",
+    "file-selector-button",
+    "first-letter",
+    "first-line",
+    "grammar-error",
+    "marker",
+    "placeholder",
+    "selection",
+    "spelling-error",
+    "target-text",
+];
+
+impl Pseudo {
+    /// Fails when `name` is neither a pseudo-class nor a pseudo-element, suggesting the closest
+    /// one, or breakpoint, as the name is more likely a typo. Vendor-prefixed names, e.g.
+    /// `-webkit-autofill`, are always accepted.
+    fn new(name: String, config: &Config) -> Result<Self, String> {
+        let known = PSEUDO_CLASSES.iter().chain(PSEUDO_ELEMENTS);
+        if name.starts_with('-') || known.clone().any(|known| *known == name) {
+            return Ok(Pseudo { name });
         }
-        input.parse::<syn::Token![:]>()?;
+        let breakpoints = config.breakpoints.iter().map(|bp| bp.name.as_str());
+        match property::closest(&name, known.copied().chain(breakpoints)) {
+            Some(suggestion) => Err(format!(
+                "unknown pseudo-class or breakpoint `{}`, did you mean `{}`?",
+                name, suggestion
+            )),
+            None => Err(format!("unknown pseudo-class or breakpoint `{}`", name)),
+        }
+    }
 
-        let mut values = vec![];
-        if input.peek(syn::Token![$]) {
-            input.parse::<syn::Token![$]>().unwrap();
-            let ident = input.parse::<syn::Ident>().map_err(|e| {
-                syn::Error::new(
-                    Span::call_site(),
-                    format!("expected ident after `$`, found `{}`", e),
-                )
-            })?;
-            let val = format!("var(--{})", ident.to_string());
-            values.push(Value {
-                key: ident.to_string().replace(" ", "_"),
-                value: val,
-            });
-            if input.peek(syn::Token![,]) {
-                input.parse::<syn::Token![,]>().unwrap();
-            }
+    fn is_element(&self) -> bool {
+        PSEUDO_ELEMENTS.contains(&self.name.as_str())
+    }
+
+    fn selector(&self) -> String {
+        if self.is_element() {
+            format!("::{}", self.name)
         } else {
-            while !input.is_empty() && !input.peek(syn::Token![,]) {
-                if let Ok(value) = input.parse::<syn::Ident>() {
-                    values.push(Value {
-                        key: value.to_string().replace(" ", "_"),
-                        value: value.to_string(),
-                    });
-                } else if let Ok(value) = input.parse::<syn::LitInt>() {
-                    let val = NumericValue {
-                        value: value.base10_parse::<u32>().expect("not a valid integer"),
-                        unit: value.suffix().to_string(),
-                    };
-                    let val_str = format!("{}{}", val.value, val.unit);
-                    values.push(Value {
-                        key: val_str.replace(" ", "_"),
-                        value: val_str,
-                    });
-                } else {
-                    return Err(input.error(format!("error parsing value for prop: {}", prop)));
-                }
+            format!(":{}", self.name)
+        }
+    }
+}
+
+/// Escapes the characters that have a meaning in readable class names, or in the HTML attribute
+/// they end up in, and turns spaces into `_`.
+fn escape_class_part(part: &str) -> String {
+    let mut escaped = String::new();
+    for c in part.chars() {
+        match c {
+            '\\' | ':' | '_' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
             }
-            if input.peek(syn::Token![,]) {
-                input.parse::<syn::Token![,]>()?;
+            ' ' => escaped.push('_'),
+            // other whitespace would split the class attribute, and quotes would end it, as
+            // would the other characters with a meaning in HTML once the name is rendered
+            c if c.is_whitespace() || c.is_control() || "\"'&<>".contains(c) => {
+                escaped.push_str(&format!("\\{:x};", c as u32))
             }
+            c => escaped.push(c),
         }
-
-        Ok(Rule { prop, values })
     }
+    escaped
 }
 
-#[derive(Clone, Debug)]
-struct Value {
-    key: String,
-    value: String,
+/// A short class name derived from the readable one: a letter followed by 5 base-36 digits.
+fn hashed_class_name(readable: &str) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut hash = fnv1a(readable);
+    // class names can't start with a digit
+    let mut name = String::from((b'a' + (hash % 26) as u8) as char);
+    hash /= 26;
+    for _ in 0..5 {
+        name.push(DIGITS[(hash % 36) as usize] as char);
+        hash /= 36;
+    }
+    name
 }
 
-struct NumericValue {
-    value: u32,
-    unit: String,
+/// Serializes a class name as a CSS identifier, escaping what would otherwise be invalid in a
+/// selector, following https://drafts.csswg.org/cssom/#serialize-an-identifier.
+fn escape_ident(ident: &str) -> String {
+    let starts_with_dash = ident.starts_with('-');
+    let mut escaped = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        match c {
+            '\0' => escaped.push('\u{FFFD}'),
+            '\u{1}'..='\u{1F}' | '\u{7F}' => escaped.push_str(&format!("\\{:x} ", c as u32)),
+            '0'..='9' if i == 0 || (i == 1 && starts_with_dash) => {
+                escaped.push_str(&format!("\\{:x} ", c as u32))
+            }
+            '-' if i == 0 && ident.len() == 1 => escaped.push_str("\\-"),
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii() => {
+                escaped.push(c)
+            }
+            c => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+        }
+    }
+    escaped
 }
 
 #[proc_macro]
@@ -288,241 +775,434 @@ pub fn csm_defs(tokens: TokenStream) -> TokenStream {
 }
 
 fn csm_colors_impl(input: TokenStream) -> TokenStream {
-    let defs = parse_macro_input!(input as TokenDefs);
+    let cx = match Context::load_or_error() {
+        Ok(cx) => cx,
+        Err(e) => return e,
+    };
+    let defs = match cx.parse(input, TokenDefs::parse) {
+        Ok(defs) => defs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let span = defs
+        .name
+        .as_ref()
+        .map_or_else(Span::call_site, |name| name.span());
+    write_defs(&defs, &cx, span)
+}
+
+/// Writes the CSS of `csm_defs!`, or of the tokens it was given by `include_tokens!`, and
+/// generates their constants.
+fn write_defs(defs: &TokenDefs, cx: &Context, span: Span) -> TokenStream {
+    if let Err(e) = defs.check_conflicts(cx) {
+        return e.to_compile_error().into();
+    }
+
+    let id = defs.file_id();
+    let origin = match unique_origin(&cx.config, &id, span, |other| {
+        match (&defs.name, &defs.file) {
+            (Some(name), _) => format!(
+                "variables named `{}` are already defined at {}, rename one of them",
+                name, other
+            ),
+            (None, Some(file)) => format!("`{}` is already included at {}", file, other),
+            (None, None) => format!(
+                "variables without a name are already defined at {}, name one of them, e.g. \
+             `csm_defs! {{ colors, .. }}`",
+                other
+            ),
+        }
+    }) {
+        Ok(origin) => origin,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     // write file
-    let out_dir = Path::new(OUTPUT_DIR);
-    fs::create_dir_all(out_dir.join("css")).expect("failed to create dir");
-    let out_file = out_dir.join("css").join("_csm_defs.css");
-    write(Path::new(&out_file), &defs.to_css());
+    let out_dir = cx.config.out_dir.as_path();
+    let entry = Entry {
+        variables: defs
+            .tokens
+            .values()
+            .map(|def| (def.name.clone(), def.value.clone()))
+            .collect(),
+        origin,
+        file: call_site_file(),
+        ..Default::default()
+    };
+    let written = Manifest::record(out_dir, &id, entry).and_then(|crate_dir| match crate_dir {
+        Some(crate_dir) => write(&crate_dir.join(format!("{}.css", id)), &defs.to_css()),
+        None => Ok(()),
+    });
+    if let Err(e) = written {
+        return io_error(span, e);
+    }
 
-    write_bundle(out_dir);
+    let defs = defs.to_tokens();
+    let track = track_config(&cx.config);
+    quote! {
+        #defs
+        #track
+    }
+    .into()
+}
+
+/// Overrides variables defined by `csm_defs!` when the theme is active, that is when the root
+/// element has a `data-theme` attribute with the name of the theme. Themes named `dark` or
+/// `light` also apply when no theme is set and the user prefers that color scheme.
+#[proc_macro]
+pub fn csm_theme(tokens: TokenStream) -> TokenStream {
+    let cx = match Context::load_or_error() {
+        Ok(cx) => cx,
+        Err(e) => return e,
+    };
+    let theme = match cx.parse(tokens, TokenDefs::parse) {
+        Ok(theme) => theme,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let Some(name) = &theme.name else {
+        return call_site_error("expected the name of the theme, e.g. `csm_theme! { dark, .. }`");
+    };
+    let config = &cx.config;
+
+    // a theme can only override variables that exist
+    if config.check_variables {
+        let defined = cx.visible_variables();
+        if !defined.is_empty() {
+            for def in theme.tokens.values() {
+                if !defined.iter().any(|variable| variable.name == def.name) {
+                    let message = undefined_variable(&def.name, &defined);
+                    return syn::Error::new(def.span, message).to_compile_error().into();
+                }
+            }
+        }
+    }
 
-    quote! {}.into()
+    let id = format!("_csm_theme-{}-{}", name, call_site_hash());
+    let entry = Entry {
+        origin: call_site_origin(),
+        file: call_site_file(),
+        ..Default::default()
+    };
+    let written =
+        Manifest::record(&config.out_dir, &id, entry).and_then(|crate_dir| match crate_dir {
+            Some(crate_dir) => write(
+                &crate_dir.join(format!("{}.css", id)),
+                &theme.to_theme_css(),
+            ),
+            None => Ok(()),
+        });
+    if let Err(e) = written {
+        return io_error(name.span(), e);
+    }
+
+    let theme = theme.to_tokens();
+    let track = track_config(config);
+    quote! {
+        #theme
+        #track
+    }
+    .into()
 }
 
+/// Defines the tokens of a W3C Design Tokens, or Style Dictionary, JSON file as CSS variables,
+/// like `csm_defs!` would.
+#[proc_macro]
+pub fn include_tokens(tokens: TokenStream) -> TokenStream {
+    design_tokens::include_tokens_impl(tokens)
+}
+
+/// The variables defined by a `csm_defs!`, optionally named, e.g. `csm_defs! { colors, ... }`.
 #[derive(Debug)]
 struct TokenDefs {
-    tokens: HashMap<String, TokenDef>,
+    name: Option<syn::Ident>,
+    /// The file the tokens were read from, for `include_tokens!`.
+    file: Option<String>,
+    tokens: BTreeMap<String, TokenDef>,
 }
 
-impl Parse for TokenDefs {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut tokens = HashMap::new();
+impl TokenDefs {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let name = if input.peek(syn::Ident) && input.peek2(syn::Token![,]) {
+            let name = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![,]>()?;
+            Some(name)
+        } else {
+            None
+        };
+
+        let mut defs = TokenDefs {
+            name,
+            file: None,
+            tokens: BTreeMap::new(),
+        };
         while !input.is_empty() {
-            let def = input.parse::<TokenDef>()?;
-            tokens.insert(def.name.clone(), def);
-            if input.is_empty() {
-                break;
-            }
+            defs.insert(TokenDef::parse(input, cx)?)?;
         }
 
-        Ok(TokenDefs { tokens })
+        Ok(defs)
+    }
+
+    fn insert(&mut self, def: TokenDef) -> syn::Result<()> {
+        if self.tokens.contains_key(&def.name) {
+            return Err(syn::Error::new(
+                def.span,
+                format!("variable `{}` is defined twice", def.name),
+            ));
+        }
+        let ident = token_ident_name(&def.name);
+        if syn::parse_str::<syn::Ident>(&ident).is_err() {
+            return Err(syn::Error::new(
+                def.span,
+                format!(
+                    "variable `{}` can't have a constant, `{}` isn't a valid identifier",
+                    def.name, ident
+                ),
+            ));
+        }
+        let ident = token_ident(&def.name);
+        if let Some(other) = self
+            .tokens
+            .values()
+            .find(|other| token_ident(&other.name) == ident)
+        {
+            return Err(syn::Error::new(
+                def.span,
+                format!(
+                    "`{}` and `{}` would both generate the constant `{}`",
+                    other.name, def.name, ident
+                ),
+            ));
+        }
+        self.tokens.insert(def.name.clone(), def);
+        Ok(())
+    }
+
+    /// Each `csm_defs!` gets its own file, named after it. An unnamed `include_tokens!` is
+    /// named after the file it includes instead, so that it doesn't clash with `csm_defs!`.
+    fn file_id(&self) -> String {
+        match (&self.name, &self.file) {
+            (Some(name), _) => format!("_csm_defs-{}", name),
+            (None, Some(file)) => format!("_csm_tokens-{:08x}", fnv1a(file) as u32),
+            (None, None) => "_csm_defs".to_string(),
+        }
+    }
+
+    /// Makes sure that no other `csm_defs!` gives a different value to the same variable,
+    /// since only one of them would apply.
+    fn check_conflicts(&self, cx: &Context) -> syn::Result<()> {
+        let id = self.file_id();
+        let others = cx
+            .variables()
+            .iter()
+            .filter(|variable| !variable.outdated && !variable.is_defined_by(&id));
+        for other in others {
+            let Some(def) = self.tokens.get(&other.name) else {
+                continue;
+            };
+            if def.value != other.value {
+                let defined_by = match other.id.strip_prefix("_csm_defs-") {
+                    Some(name) => format!("`csm_defs! {{ {}, .. }}`", name),
+                    None if other.id.starts_with("_csm_tokens-") => "`include_tokens!`".to_string(),
+                    None => "`csm_defs!`".to_string(),
+                };
+                return Err(syn::Error::new(
+                    def.span,
+                    format!(
+                        "variable `{}` is already defined as `{}` by {} in `{}`",
+                        def.name, other.value, defined_by, other.crate_key
+                    ),
+                ));
+            }
+        }
+        Ok(())
     }
-}
 
-impl TokenDefs {
     fn to_css(&self) -> String {
+        self.to_block(":root")
+    }
+
+    /// The overrides of a theme. `:root[data-theme]` is more specific than the `:root` of
+    /// `csm_defs!`, so the themes win regardless of the order of the bundle.
+    fn to_theme_css(&self) -> String {
+        let name = self
+            .name
+            .as_ref()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        let mut css = self.to_block(&format!(":root[data-theme=\"{}\"]", name));
+        if name == "dark" || name == "light" {
+            css.push_str(&format!(
+                "@media (prefers-color-scheme: {}) {{{}}}",
+                name,
+                self.to_block(":root:not([data-theme])")
+            ));
+        }
+        css
+    }
+
+    fn to_block(&self, selector: &str) -> String {
         let mut css = String::new();
 
-        css.push_str(":root {");
+        css.push_str(selector);
+        css.push_str(" {");
 
-        for (_, def) in &self.tokens {
+        for def in self.tokens.values() {
             css.push_str("--");
             css.push_str(def.name.as_str());
             css.push_str(": ");
             css.push_str(def.value.as_str());
-            css.push_str(";");
+            css.push(';');
         }
 
-        css.push_str("}");
+        css.push('}');
 
         css
     }
+
+    /// Generates a module named after the definitions, or `tokens` when they have no name,
+    /// with a [`csm::Token`] constant for each of them.
+    fn to_tokens(&self) -> TokenStream2 {
+        let module = match &self.name {
+            Some(name) => name.clone(),
+            None => syn::Ident::new("tokens", Span::call_site()),
+        };
+        let consts = self.tokens.values().map(|def| {
+            let ident = token_ident(&def.name);
+            let name = &def.name;
+            let value = self.resolve(&def.value, 0);
+            let var = format!("var(--{})", def.name);
+            let doc = format!("`--{}: {}`", def.name, def.value);
+            quote! {
+                #[doc = #doc]
+                pub const #ident: ::csm::Token = ::csm::Token {
+                    name: #name,
+                    value: #value,
+                    var: #var,
+                };
+            }
+        });
+        quote! {
+            pub mod #module {
+                #(#consts)*
+            }
+        }
+    }
+
+    /// Replaces the references to the variables defined here with their value, so that tokens
+    /// can be used outside of CSS.
+    fn resolve(&self, value: &str, depth: usize) -> String {
+        let mut resolved = value.to_string();
+        // guards against cycles
+        if depth > self.tokens.len() {
+            return resolved;
+        }
+        for def in self.tokens.values() {
+            let var = format!("var(--{})", def.name);
+            if resolved.contains(&var) {
+                resolved = resolved.replace(&var, &self.resolve(&def.value, depth + 1));
+            }
+        }
+        resolved
+    }
+}
+
+/// The name of the constant generated for a variable, e.g. `SPACING_4` for `spacing-4`.
+fn token_ident_name(name: &str) -> String {
+    let mut ident = name.to_uppercase().replace('-', "_");
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+/// The constant generated for a variable, whose name [`TokenDefs::insert`] made sure is valid.
+fn token_ident(name: &str) -> syn::Ident {
+    syn::Ident::new(&token_ident_name(name), Span::call_site())
 }
 
 #[derive(Debug)]
 struct TokenDef {
     name: String,
     value: String,
+    span: Span,
 }
 
-impl Parse for TokenDef {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut name = String::new();
-        while !input.is_empty() && !input.peek(syn::Token![:]) {
-            if let Ok(value) = input.parse::<syn::Ident>() {
-                name.push_str(value.to_string().as_str());
-            } else if let Ok(_) = input.parse::<syn::Token![-]>() {
-                name.push_str("-");
-            } else {
-                panic!("unexpected token");
-            }
-        }
+impl TokenDef {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let span = input.span();
+        let name = parse_name(input)?;
         input.parse::<syn::Token![:]>()?;
 
-        let mut value = String::new();
+        // a definition accepts the same values as a declaration
+        let value = Value::parse_declaration(input, cx)?.to_css();
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+        }
 
-        if let Ok(_) = input.parse::<syn::Token![$]>() {
-            // var reference
-            let ident = input.parse::<syn::Ident>()?;
-            value.push_str(format!("var(--{})", ident.to_string()).as_str());
-        } else {
-            while !input.is_empty() && !input.peek(syn::Token![,]) {
-                input.parse::<syn::Token![#]>()?;
-                value.push_str("#");
+        Ok(TokenDef { name, value, span })
+    }
+}
 
-                let ident = input.parse::<syn::Ident>()?;
-                value.push_str(ident.to_string().as_str());
-            }
-        }
+/// Expands to a `csm::Bundle` holding the bundled CSS of the crate and its dependencies.
+#[proc_macro]
+pub fn include_bundle(tokens: TokenStream) -> TokenStream {
+    if !tokens.is_empty() {
+        return call_site_error("include_bundle! takes no arguments");
+    }
+    let config = match Context::load_or_error() {
+        Ok(cx) => cx.config,
+        Err(e) => return e,
+    };
 
-        if input.peek(syn::Token![,]) {
-            input.parse::<syn::Token![,]>()?;
+    let css = match csm_build::bundle_css(&config, Scope::CurrentCrate) {
+        Ok(css) => css,
+        Err(e) => return call_site_error(e),
+    };
+    let hash = csm_build::content_hash(&css);
+    let href = config.bundle_href(&hash);
+    let track = track_config(&config);
+
+    quote! {{
+        #track
+        ::csm::Bundle {
+            css: #css,
+            hash: #hash,
+            href: #href,
         }
+    }}
+    .into()
+}
+
+/// Writes the bundle of the crate and its dependencies and expands to the URL of its hashed
+/// version, e.g. `"/bundle.1a2b3c4d.css"`.
+#[proc_macro]
+pub fn bundle_href(tokens: TokenStream) -> TokenStream {
+    if !tokens.is_empty() {
+        return call_site_error("bundle_href! takes no arguments");
+    }
+    let config = match Context::load_or_error() {
+        Ok(cx) => cx.config,
+        Err(e) => return e,
+    };
+
+    let files = match csm_build::write_bundle(&config, Scope::CurrentCrate) {
+        Ok(files) => files,
+        Err(e) => return call_site_error(e),
+    };
+    if let Err(e) = Manifest::record_bundle(&config.out_dir, &files.hash, call_site_file()) {
+        return io_error(Span::call_site(), e);
+    }
+    let href = config.bundle_href(&files.hash);
+    let track = track_config(&config);
 
-        Ok(TokenDef { name, value })
-    }
-}
-
-// TODO: recipe!{} macro should be rethinked entirely.
-
-// #[proc_macro]
-// pub fn recipe(tokens: TokenStream) -> TokenStream {
-//     recipe_impl(tokens)
-// }
-//
-// fn recipe_impl(input: TokenStream) -> TokenStream {
-//     let recipe = parse_macro_input!(input as Recipe);
-//
-//     let mut matrix = vec![vec![]];
-//     for (_, variant) in &recipe.variants {
-//         let mut new_rows = Vec::new();
-//         for row in &matrix {
-//             for (variant_choice, variant_rules) in variant {
-//                 let mut new_row = row.clone();
-//                 new_row.push((variant_choice.clone(), variant_rules));
-//                 new_rows.push(new_row);
-//             }
-//         }
-//         matrix = new_rows;
-//     }
-//
-//     let macro_name = &recipe.name;
-//
-//     let mut m_rules = vec![];
-//     for row in matrix {
-//         let names = row.iter().map(|(name, _)| name).collect::<Vec<_>>();
-//         let mut r = recipe.base.clone();
-//         for (_, rules) in &row {
-//             r = r.merge(rules);
-//         }
-//         let class_name = format!(
-//             "{}__{}",
-//             &recipe.name,
-//             names
-//                 .iter()
-//                 .map(|s| s.to_string())
-//                 .collect::<Vec<String>>()
-//                 .join("_")
-//         );
-//         let css = format!(
-//             ".{} {{\n{}\n}}",
-//             class_name,
-//             r.0.iter()
-//                 .map(|(_, rule)| rule.to_css())
-//                 .collect::<Vec<String>>()
-//                 .join("\n")
-//         );
-//         m_rules.push(quote! {
-//             (#(#names),*) => {
-//                 (#class_name, #css)
-//             };
-//         });
-//     }
-//
-//     quote! {
-//         macro_rules! #macro_name {
-//             #(#m_rules)*
-//         }
-//     }
-//     .into()
-// }
-//
-// #[derive(Debug)]
-// struct Recipe {
-//     name: syn::Ident,
-//     base: Rules,
-//     variants: Vec<(String, HashMap<String, Rules>)>,
-//     defaults: Vec<(String, String)>,
-// }
-//
-// impl Parse for Recipe {
-//     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-//         let name = input.parse::<syn::Ident>()?;
-//         let mut base = None;
-//         let mut variants = vec![];
-//         let mut defaults = vec![];
-//
-//         while !input.is_empty() {
-//             if input.peek(syn::Ident) {
-//                 let ident = input.parse::<syn::Ident>()?;
-//                 input.parse::<syn::Token![:]>()?;
-//                 let body;
-//                 braced!(body in input);
-//
-//                 if ident.to_string() == "base" {
-//                     base = Some(body.parse::<Rules>()?);
-//                 } else if ident.to_string() == "default" {
-//                     let ident = body.parse::<syn::Ident>()?;
-//                     defaults.push(("default".to_string(), ident.to_string()));
-//                 } else if ident.to_string() == "variants" {
-//                     while !body.is_empty() {
-//                         let variant_name = body.parse::<syn::Ident>()?;
-//                         body.parse::<syn::Token![:]>()?;
-//                         let variant_body;
-//                         braced!(variant_body in body);
-//
-//                         let mut variant_choices = HashMap::new();
-//
-//                         while !variant_body.is_empty() {
-//                             let ident = variant_body.parse::<syn::Ident>()?;
-//                             variant_body.parse::<syn::Token![:]>()?;
-//                             let choice_body;
-//                             braced!(choice_body in variant_body);
-//                             variant_choices
-//                                 .insert(ident.to_string(), choice_body.parse::<Rules>()?);
-//                             if variant_body.peek(syn::Token![,]) {
-//                                 variant_body.parse::<syn::Token![,]>()?;
-//                             }
-//                         }
-//
-//                         variants.push((variant_name.to_string(), variant_choices));
-//                         if body.peek(syn::Token![,]) {
-//                             body.parse::<syn::Token![,]>()?;
-//                         }
-//                     }
-//                 } else {
-//                     return Err(syn::Error::new(
-//                         Span::call_site(),
-//                         format!(
-//                             "expected base, variants, or default, got: `{}`",
-//                             ident.to_string()
-//                         ),
-//                     ));
-//                 }
-//             } else if input.peek(syn::Token![,]) {
-//                 input.parse::<syn::Token![,]>()?;
-//             } else {
-//                 return Err(input.error("unexpected token"));
-//             }
-//         }
-//
-//         Ok(Recipe {
-//             name,
-//             base: base.expect("base is required"),
-//             variants,
-//             defaults,
-//         })
-//     }
-// }
+    quote! {{
+        #track
+        #href
+    }}
+    .into()
+}
+
+#[proc_macro]
+pub fn recipe(tokens: TokenStream) -> TokenStream {
+    recipe::recipe_impl(tokens)
+}
@@ -0,0 +1,486 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{braced, bracketed, ext::IdentExt, parse::ParseStream};
+
+use csm_build::ClassNames;
+
+use crate::{
+    call_site_hash, call_site_origin, io_error, track_config, write_rules, Context, Rule, Rules,
+};
+
+pub fn recipe_impl(input: TokenStream) -> TokenStream {
+    let cx = match Context::load_or_error() {
+        Ok(cx) => cx,
+        Err(e) => return e,
+    };
+    let recipe = match cx.parse(input, Recipe::parse) {
+        Ok(recipe) => recipe,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let config = &cx.config;
+
+    // every rule that can possibly be selected ends up in the bundle
+    let mut all_rules: Vec<Rule> = vec![];
+    let choices = recipe
+        .variants
+        .iter()
+        .flat_map(|variant| variant.choices.iter().map(|choice| &choice.rules));
+    let compounds = recipe.compound_variants.iter().map(|c| &c.rules);
+    for rules in std::iter::once(&recipe.base)
+        .chain(choices)
+        .chain(compounds)
+    {
+        for rule in &rules.0 {
+            if !all_rules
+                .iter()
+                .any(|r| r.readable_class_name() == rule.readable_class_name())
+            {
+                all_rules.push(rule.clone());
+            }
+        }
+    }
+
+    let out_dir = config.out_dir.as_path();
+    let origin = call_site_origin();
+    if let Err(e) = write_rules(out_dir, &recipe.file_id(), &all_rules, config, origin) {
+        return io_error(recipe.name.span(), e);
+    }
+
+    // referencing the token constants makes sure they exist
+    let paths = all_rules.iter().flat_map(|rule| rule.value.token_paths());
+    let recipe = recipe.to_tokens(config.class_names);
+    let track = track_config(config);
+    let warnings = cx.warnings();
+    quote! {
+        #track
+        #warnings
+        #(const _: ::csm::Token = #paths;)*
+        #recipe
+    }
+    .into()
+}
+
+/// A component style made of base rules, variants to pick from, and compound variants applied
+/// when a given combination of variants is selected.
+struct Recipe {
+    vis: syn::Visibility,
+    name: syn::Ident,
+    base: Rules,
+    variants: Vec<Variant>,
+    compound_variants: Vec<CompoundVariant>,
+}
+
+struct Variant {
+    name: syn::Ident,
+    choices: Vec<Choice>,
+    default: Option<syn::Ident>,
+}
+
+struct Choice {
+    name: syn::Ident,
+    rules: Rules,
+}
+
+struct CompoundVariant {
+    conditions: Vec<(syn::Ident, syn::Ident)>,
+    rules: Rules,
+}
+
+impl Recipe {
+    /// Name of the CSS file, `-` can't appear in a `csm!` id so the two never clash. Recipes
+    /// with the same name can be defined in different modules, so it includes where the recipe
+    /// is defined.
+    fn file_id(&self) -> String {
+        format!("recipe-{}-{}", self.name, call_site_hash())
+    }
+
+    /// Every combination of choices, one per variant, in declaration order.
+    fn combinations(&self) -> Vec<Vec<&Choice>> {
+        let mut matrix = vec![vec![]];
+        for variant in &self.variants {
+            let mut new_rows = Vec::new();
+            for row in &matrix {
+                for choice in &variant.choices {
+                    let mut new_row = row.clone();
+                    new_row.push(choice);
+                    new_rows.push(new_row);
+                }
+            }
+            matrix = new_rows;
+        }
+        matrix
+    }
+
+    /// The class names applied when the given choices are selected.
+    fn class_names(&self, selected: &[&Choice], class_names: ClassNames) -> String {
+        let mut rules = self.base.clone();
+        for choice in selected {
+            rules = rules.merge(&choice.rules);
+        }
+        for compound in &self.compound_variants {
+            let applies = compound.conditions.iter().all(|(variant, choice)| {
+                self.variants
+                    .iter()
+                    .zip(selected)
+                    .any(|(v, c)| &v.name == variant && &c.name == choice)
+            });
+            if applies {
+                rules = rules.merge(&compound.rules);
+            }
+        }
+        rules.class_names(class_names)
+    }
+
+    fn to_tokens(&self, class_names: ClassNames) -> TokenStream2 {
+        let vis = &self.vis;
+        let name = &self.name;
+
+        let enums = self.variants.iter().map(|variant| {
+            let enum_name = variant.enum_name();
+            let variant_str = variant.name.unraw().to_string();
+            let choices = variant
+                .choices
+                .iter()
+                .map(|c| c.variant_name())
+                .collect::<Vec<_>>();
+            let choice_strs = variant
+                .choices
+                .iter()
+                .map(|c| c.name.unraw().to_string())
+                .collect::<Vec<_>>();
+            let default = variant.default.as_ref().map(|default| {
+                let choice = pascal_case_ident(default);
+                quote! {
+                    impl Default for #enum_name {
+                        fn default() -> Self {
+                            #enum_name::#choice
+                        }
+                    }
+                }
+            });
+            quote! {
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+                pub enum #enum_name {
+                    #(#choices),*
+                }
+
+                impl #enum_name {
+                    /// The name of the choice, as written in the recipe.
+                    pub fn as_str(&self) -> &'static str {
+                        match self {
+                            #(#enum_name::#choices => #choice_strs,)*
+                        }
+                    }
+                }
+
+                impl ::core::str::FromStr for #enum_name {
+                    type Err = ::csm::UnknownChoice;
+
+                    fn from_str(s: &str) -> Result<Self, Self::Err> {
+                        match s {
+                            #(#choice_strs => Ok(#enum_name::#choices),)*
+                            _ => Err(::csm::UnknownChoice {
+                                variant: #variant_str,
+                                choice: s.to_string(),
+                            }),
+                        }
+                    }
+                }
+
+                #default
+            }
+        });
+
+        let fields = self
+            .variants
+            .iter()
+            .map(|variant| &variant.name)
+            .collect::<Vec<_>>();
+        let field_types = self.variants.iter().map(|variant| variant.enum_name());
+        let derive_default = self
+            .variants
+            .iter()
+            .all(|variant| variant.default.is_some())
+            .then(|| quote! { #[derive(Default)] });
+
+        // the classes of every combination are laid out so that the index of a combination is
+        // a mixed radix number, with one digit per variant
+        let classes = self
+            .combinations()
+            .into_iter()
+            .map(|row| self.class_names(&row, class_names))
+            .collect::<Vec<_>>();
+        let len = classes.len();
+        let index = self
+            .variants
+            .iter()
+            .fold(None, |index, variant| {
+                let field = &variant.name;
+                let radix = variant.choices.len();
+                Some(match index {
+                    Some(index) => quote! { (#index) * #radix + self.#field as usize },
+                    None => quote! { self.#field as usize },
+                })
+            })
+            .unwrap_or_else(|| quote! { 0 });
+
+        let args = self.variants.iter().map(|variant| {
+            let arg = &variant.name;
+            let enum_name = variant.enum_name();
+            quote! { #arg: #name::#enum_name }
+        });
+
+        quote! {
+            #vis mod #name {
+                #(#enums)*
+
+                /// The selected choice for each variant.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+                #derive_default
+                pub struct Variants {
+                    #(pub #fields: #field_types,)*
+                }
+
+                const CLASSES: [&str; #len] = [#(#classes),*];
+
+                impl Variants {
+                    /// The classes to apply for the selected choices.
+                    pub fn class(&self) -> &'static str {
+                        CLASSES[#index]
+                    }
+                }
+            }
+
+            #vis fn #name(#(#args),*) -> &'static str {
+                #name::Variants { #(#fields),* }.class()
+            }
+        }
+    }
+}
+
+impl Variant {
+    fn enum_name(&self) -> syn::Ident {
+        pascal_case_ident(&self.name)
+    }
+}
+
+impl Choice {
+    fn variant_name(&self) -> syn::Ident {
+        pascal_case_ident(&self.name)
+    }
+}
+
+/// `r#type` becomes `Type`, as raw identifiers are only needed for keywords.
+fn pascal_case_ident(ident: &syn::Ident) -> syn::Ident {
+    let pascal = ident
+        .unraw()
+        .to_string()
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    format_ident!("{}", pascal, span = ident.span())
+}
+
+impl Recipe {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let vis = input.parse::<syn::Visibility>()?;
+        let name = input.parse::<syn::Ident>()?;
+        let mut base = Rules::default();
+        let mut variants: Vec<Variant> = vec![];
+        let mut defaults = vec![];
+        let mut compound_variants = vec![];
+
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let section = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![:]>()?;
+            match section.to_string().as_str() {
+                "base" => {
+                    let body;
+                    braced!(body in input);
+                    base = Rules::parse(&body, cx)?;
+                }
+                "variants" => {
+                    let body;
+                    braced!(body in input);
+                    while !body.is_empty() {
+                        let variant = Variant::parse(&body, cx)?;
+                        if variants
+                            .iter()
+                            .any(|v| v.name.unraw() == variant.name.unraw())
+                        {
+                            return Err(syn::Error::new(
+                                variant.name.span(),
+                                format!("duplicate variant `{}`", variant.name.unraw()),
+                            ));
+                        }
+                        variants.push(variant);
+                        if !body.is_empty() {
+                            body.parse::<syn::Token![,]>()?;
+                        }
+                    }
+                }
+                "default_variants" => {
+                    let body;
+                    braced!(body in input);
+                    while !body.is_empty() {
+                        let variant = body.parse::<syn::Ident>()?;
+                        body.parse::<syn::Token![:]>()?;
+                        let choice = body.parse::<syn::Ident>()?;
+                        if defaults.iter().any(|(v, _)| v == &variant) {
+                            return Err(syn::Error::new(
+                                variant.span(),
+                                format!("duplicate default for variant `{}`", variant.unraw()),
+                            ));
+                        }
+                        defaults.push((variant, choice));
+                        if !body.is_empty() {
+                            body.parse::<syn::Token![,]>()?;
+                        }
+                    }
+                }
+                "compound_variants" => {
+                    let body;
+                    bracketed!(body in input);
+                    while !body.is_empty() {
+                        compound_variants.push(CompoundVariant::parse(&body, cx)?);
+                        if !body.is_empty() {
+                            body.parse::<syn::Token![,]>()?;
+                        }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        section.span(),
+                        format!(
+                            "expected base, variants, default_variants or compound_variants, got: `{}`",
+                            section
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (variant, choice) in defaults {
+            let v = find_choice(&variants, &variant, &choice)?;
+            variants[v].default = Some(choice);
+        }
+        for compound in &compound_variants {
+            for (variant, choice) in &compound.conditions {
+                find_choice(&variants, variant, choice)?;
+            }
+        }
+
+        Ok(Recipe {
+            vis,
+            name,
+            base,
+            variants,
+            compound_variants,
+        })
+    }
+}
+
+/// Checks that `variant` exists and has a `choice`, returning the index of the variant.
+fn find_choice(
+    variants: &[Variant],
+    variant: &syn::Ident,
+    choice: &syn::Ident,
+) -> syn::Result<usize> {
+    let v = variants
+        .iter()
+        .position(|v| &v.name == variant)
+        .ok_or_else(|| syn::Error::new(variant.span(), format!("unknown variant `{}`", variant)))?;
+    if !variants[v].choices.iter().any(|c| &c.name == choice) {
+        return Err(syn::Error::new(
+            choice.span(),
+            format!("unknown choice `{}` for variant `{}`", choice, variant),
+        ));
+    }
+    Ok(v)
+}
+
+impl Variant {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let name = input.parse::<syn::Ident>()?;
+        input.parse::<syn::Token![:]>()?;
+        let body;
+        braced!(body in input);
+
+        let mut choices: Vec<Choice> = vec![];
+        while !body.is_empty() {
+            let choice_name = body.parse::<syn::Ident>()?;
+            body.parse::<syn::Token![:]>()?;
+            let choice_body;
+            braced!(choice_body in body);
+            if choices
+                .iter()
+                .any(|c| c.name.unraw() == choice_name.unraw())
+            {
+                return Err(syn::Error::new(
+                    choice_name.span(),
+                    format!("duplicate choice `{}`", choice_name.unraw()),
+                ));
+            }
+            choices.push(Choice {
+                name: choice_name,
+                rules: Rules::parse(&choice_body, cx)?,
+            });
+            if !body.is_empty() {
+                body.parse::<syn::Token![,]>()?;
+            }
+        }
+        if choices.is_empty() {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("variant `{}` has no choices", name),
+            ));
+        }
+
+        Ok(Variant {
+            name,
+            choices,
+            default: None,
+        })
+    }
+}
+
+impl CompoundVariant {
+    fn parse(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let body;
+        braced!(body in input);
+
+        let mut conditions = vec![];
+        let mut rules = None;
+        while !body.is_empty() {
+            let key = body.parse::<syn::Ident>()?;
+            body.parse::<syn::Token![:]>()?;
+            if key == "css" {
+                let css_body;
+                braced!(css_body in body);
+                rules = Some(Rules::parse(&css_body, cx)?);
+            } else {
+                conditions.push((key, body.parse::<syn::Ident>()?));
+            }
+            if !body.is_empty() {
+                body.parse::<syn::Token![,]>()?;
+            }
+        }
+
+        Ok(CompoundVariant {
+            conditions,
+            rules: rules.ok_or_else(|| body.error("compound variant is missing `css: { ... }`"))?,
+        })
+    }
+}
@@ -0,0 +1,451 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{ext::IdentExt, parenthesized, parse::ParseStream};
+
+use crate::{parse_name, token_ident, tokens_since, Context};
+
+/// Functions whose arguments are math expressions, where `+` and `-` between two operands are
+/// operators rather than signs.
+const MATH_FUNCTIONS: &[&str] = &[
+    "abs", "acos", "asin", "atan", "atan2", "calc", "clamp", "cos", "exp", "hypot", "log", "max",
+    "min", "mod", "pow", "rem", "round", "sign", "sin", "sqrt", "tan",
+];
+
+/// A CSS value, e.g. `1px solid $border` or `"Inter", sans-serif`.
+#[derive(Clone, Debug)]
+pub struct Value(Vec<Component>);
+
+#[derive(Clone, Debug)]
+enum Component {
+    /// `flex`, `sans-serif`, `-webkit-box`
+    Ident(String),
+    /// `1`, `-0.5rem`, `50%`
+    Number(String),
+    /// `#fff`, without the `#`
+    Hash(String),
+    /// `"Inter"`, without the quotes
+    String(String),
+    /// `raw("2em")`, CSS that can't be written with Rust tokens, without the quotes
+    Raw(String),
+    /// `$danger`, a reference to a CSS variable, along with its tokens
+    Var(String, TokenStream2),
+    /// `tokens::DANGER`, a reference to a CSS variable through the constant generated for it
+    /// by `csm_defs!`
+    Token(String, TokenStream2),
+    /// `calc(100% - 2rem)`, or a parenthesized math expression when the name is empty
+    Function(String, Vec<Component>),
+    Comma,
+    Slash,
+    /// `+`, `-` or `*` inside a math function
+    Operator(char),
+}
+
+impl Value {
+    /// Parses the value of a declaration, up to the `,` separating it from the next one or the
+    /// `!important` flag.
+    pub fn parse_declaration(input: ParseStream, cx: &Context) -> syn::Result<Self> {
+        let components = parse_components(input, false, true, cx)?;
+        if components.is_empty() {
+            return Err(input.error("expected a value"));
+        }
+        Ok(Value(components))
+    }
+
+    pub fn to_css(&self) -> String {
+        components_to_text(&self.0, false)
+    }
+
+    /// The CSS variables referenced by the value, along with their tokens.
+    pub fn variables(&self) -> Vec<(&str, &TokenStream2)> {
+        let mut variables = vec![];
+        self.0.iter().for_each(|c| c.variables(&mut variables));
+        variables
+    }
+
+    /// The paths to the token constants referenced by the value.
+    pub fn token_paths(&self) -> Vec<&TokenStream2> {
+        let mut paths = vec![];
+        self.0.iter().for_each(|c| c.token_paths(&mut paths));
+        paths
+    }
+
+    /// The value as it appears in readable class names, which is its CSS except for variables
+    /// that are written `$name`.
+    pub fn to_class_text(&self) -> String {
+        components_to_text(&self.0, true)
+    }
+}
+
+impl Component {
+    fn variables<'a>(&'a self, variables: &mut Vec<(&'a str, &'a TokenStream2)>) {
+        match self {
+            Component::Var(name, tokens) => variables.push((name, tokens)),
+            Component::Function(_, args) => args.iter().for_each(|c| c.variables(variables)),
+            _ => {}
+        }
+    }
+
+    fn token_paths<'a>(&'a self, paths: &mut Vec<&'a TokenStream2>) {
+        match self {
+            Component::Token(_, path) => paths.push(path),
+            Component::Function(_, args) => args.iter().for_each(|c| c.token_paths(paths)),
+            _ => {}
+        }
+    }
+
+    fn is_operand(&self) -> bool {
+        !matches!(
+            self,
+            Component::Comma | Component::Slash | Component::Operator(_)
+        )
+    }
+
+    fn to_text(&self, class_name: bool) -> String {
+        match self {
+            Component::Ident(ident) => ident.clone(),
+            Component::Number(number) => number.clone(),
+            Component::Hash(hash) => format!("#{}", hash),
+            Component::String(string) => {
+                format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            Component::Raw(css) => css.clone(),
+            Component::Var(name, _) | Component::Token(name, _) if class_name => {
+                format!("${}", name)
+            }
+            Component::Var(name, _) | Component::Token(name, _) => format!("var(--{})", name),
+            Component::Function(name, args) => {
+                format!("{}({})", name, components_to_text(args, class_name))
+            }
+            Component::Comma => ",".to_string(),
+            Component::Slash => "/".to_string(),
+            Component::Operator(op) => op.to_string(),
+        }
+    }
+}
+
+fn components_to_text(components: &[Component], class_name: bool) -> String {
+    let mut text = String::new();
+    for (i, component) in components.iter().enumerate() {
+        match component {
+            Component::Comma => text.push_str(", "),
+            Component::Slash | Component::Operator(_) => {
+                text.push(' ');
+                text.push_str(&component.to_text(class_name));
+                text.push(' ');
+            }
+            _ => {
+                if i > 0 && components[i - 1].is_operand() {
+                    text.push(' ');
+                }
+                text.push_str(&component.to_text(class_name));
+            }
+        }
+    }
+    text
+}
+
+/// Parses components until the end of the input or, for a declaration, until the `,` that
+/// separates it from the next declaration.
+fn parse_components(
+    input: ParseStream,
+    math: bool,
+    declaration: bool,
+    cx: &Context,
+) -> syn::Result<Vec<Component>> {
+    let mut components: Vec<Component> = vec![];
+    while !input.is_empty() {
+        if declaration && input.peek(syn::Token![,]) && ends_declaration(input) {
+            break;
+        }
+        if declaration && input.peek(syn::Token![!]) {
+            break;
+        }
+        let after_operand = components.last().is_some_and(|c| c.is_operand());
+        components.push(parse_component(input, math, after_operand, cx)?);
+    }
+    Ok(components)
+}
+
+/// Checks whether the `,` at the start of the input separates two declarations, rather than
+/// two items of a list such as `"Inter", sans-serif`.
+fn ends_declaration(input: ParseStream) -> bool {
+    let fork = input.fork();
+    let _ = fork.parse::<syn::Token![,]>();
+    fork.is_empty()
+        || (parse_name(&fork).is_ok() && fork.peek(syn::Token![:]) && !fork.peek(syn::Token![::]))
+}
+
+fn parse_component(
+    input: ParseStream,
+    math: bool,
+    after_operand: bool,
+    cx: &Context,
+) -> syn::Result<Component> {
+    if input.peek(syn::Token![,]) {
+        input.parse::<syn::Token![,]>()?;
+        return Ok(Component::Comma);
+    }
+    if input.peek(syn::Token![/]) {
+        input.parse::<syn::Token![/]>()?;
+        return Ok(Component::Slash);
+    }
+    if math && input.peek(syn::Token![*]) {
+        input.parse::<syn::Token![*]>()?;
+        return Ok(Component::Operator('*'));
+    }
+    if math && after_operand && input.peek(syn::Token![+]) {
+        input.parse::<syn::Token![+]>()?;
+        return Ok(Component::Operator('+'));
+    }
+    if math && after_operand && input.peek(syn::Token![-]) {
+        input.parse::<syn::Token![-]>()?;
+        return Ok(Component::Operator('-'));
+    }
+
+    if input.peek(syn::Token![$]) {
+        let begin = input.cursor();
+        input.parse::<syn::Token![$]>()?;
+        let name = parse_dashed_ident(input, true)?;
+        return Ok(Component::Var(name, tokens_since(begin, input)));
+    }
+    if input.peek(syn::Token![::])
+        || (input.peek(syn::Ident::peek_any) && input.peek2(syn::Token![::]))
+    {
+        let path = input.call(syn::Path::parse_mod_style)?;
+        return Ok(Component::Token(
+            token_name(&path, cx)?,
+            path.to_token_stream(),
+        ));
+    }
+    if input.peek(syn::Token![#]) {
+        input.parse::<syn::Token![#]>()?;
+        let hash = if input.peek(syn::Ident::peek_any) {
+            input.call(syn::Ident::parse_any)?.to_string()
+        } else if input.peek(syn::LitInt) {
+            input.parse::<syn::LitInt>()?.to_string()
+        } else if input.peek(syn::LitFloat) {
+            input.parse::<syn::LitFloat>()?.to_string()
+        } else {
+            return Err(input.error("expected a hex color after `#`"));
+        };
+        return Ok(Component::Hash(hash));
+    }
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        return Ok(Component::Function(
+            String::new(),
+            parse_components(&content, math, false, cx)?,
+        ));
+    }
+    if input.peek(syn::LitStr) {
+        return Ok(Component::String(input.parse::<syn::LitStr>()?.value()));
+    }
+
+    // signs and numbers
+    let sign = if input.peek(syn::Token![-]) {
+        input.parse::<syn::Token![-]>()?;
+        "-"
+    } else if input.peek(syn::Token![+]) {
+        input.parse::<syn::Token![+]>()?;
+        "+"
+    } else {
+        ""
+    };
+    if input.peek(syn::LitInt) || input.peek(syn::LitFloat) || input.peek(syn::Token![.]) {
+        return Ok(Component::Number(format!(
+            "{}{}",
+            sign,
+            parse_number(input)?
+        )));
+    }
+    if sign == "+" {
+        return Err(input.error("expected a number after `+`"));
+    }
+
+    // identifiers and functions, `--custom` and `-webkit-box` included
+    let mut ident = sign.to_string();
+    if sign == "-" && input.peek(syn::Token![-]) {
+        input.parse::<syn::Token![-]>()?;
+        ident.push('-');
+    }
+    if !input.peek(syn::Ident::peek_any) {
+        return Err(input.error("expected a CSS value"));
+    }
+    ident.push_str(&parse_dashed_ident(input, false)?);
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        // unquoted URLs aren't made of Rust tokens, e.g. `a.png` or `data:image/png;base64,..`
+        if ident == "url" && !(content.peek(syn::LitStr) && content.peek2(syn::parse::End)) {
+            return Err(content.error("the URL must be quoted, e.g. `url(\"image.png\")`"));
+        }
+        // CSS the Rust lexer rejects, e.g. `2em` which looks like an exponent
+        if ident == "raw" {
+            if !(content.peek(syn::LitStr) && content.peek2(syn::parse::End)) {
+                return Err(content.error("expected raw CSS in a string, e.g. `raw(\"2em\")`"));
+            }
+            let css = content.parse::<syn::LitStr>()?;
+            if css.value().contains([';', '{', '}']) {
+                return Err(syn::Error::new(
+                    css.span(),
+                    "raw CSS can't contain `;`, `{` or `}`",
+                ));
+            }
+            return Ok(Component::Raw(css.value()));
+        }
+        let math = MATH_FUNCTIONS.contains(&ident.as_str());
+        return Ok(Component::Function(
+            ident,
+            parse_components(&content, math, false, cx)?,
+        ));
+    }
+    Ok(Component::Ident(ident))
+}
+
+/// Parses a number, e.g. `1`, `0.5rem` or `.5rem`, followed by an optional `%`.
+fn parse_number(input: ParseStream) -> syn::Result<String> {
+    let mut number = String::new();
+    if input.peek(syn::Token![.]) {
+        input.parse::<syn::Token![.]>()?;
+        number.push_str("0.");
+        number.push_str(&input.parse::<syn::LitInt>()?.to_string());
+    } else if input.peek(syn::LitFloat) {
+        number.push_str(&input.parse::<syn::LitFloat>()?.to_string());
+    } else {
+        number.push_str(&input.parse::<syn::LitInt>()?.to_string());
+    }
+    if input.peek(syn::Token![%]) {
+        input.parse::<syn::Token![%]>()?;
+        number.push('%');
+    }
+    Ok(number)
+}
+
+/// Parses an identifier made of words joined by dashes, e.g. `sans-serif` or `ease-in-out`.
+///
+/// Tokens don't tell whether there's whitespace around a `-`, so `a-b` and `a - b` are both
+/// parsed as `a-b`. Numbers can be part of the identifier too, e.g. `spacing-4`, but only if
+/// they have no unit, as `$gap - 1px` is more likely an operation inside `calc()`.
+fn parse_dashed_ident(input: ParseStream, allow_numbers: bool) -> syn::Result<String> {
+    let mut ident = input.call(syn::Ident::parse_any)?.to_string();
+    loop {
+        let fork = input.fork();
+        if fork.parse::<syn::Token![-]>().is_err() {
+            break;
+        }
+        if fork.peek(syn::Ident::peek_any) {
+            input.parse::<syn::Token![-]>()?;
+            ident.push('-');
+            ident.push_str(&input.call(syn::Ident::parse_any)?.to_string());
+        } else if allow_numbers
+            && fork.peek(syn::LitInt)
+            && fork.parse::<syn::LitInt>()?.suffix().is_empty()
+        {
+            input.parse::<syn::Token![-]>()?;
+            ident.push('-');
+            ident.push_str(&input.parse::<syn::LitInt>()?.to_string());
+        } else {
+            break;
+        }
+    }
+    Ok(ident)
+}
+
+/// The name of the variable a token constant was generated for.
+///
+/// The variables defined so far by the crate and its dependencies are looked up first, as `-`
+/// and `_` are both turned into `_` in constant names. Constants that aren't found there, e.g.
+/// because they are defined later in the same crate, are assumed to have been defined with
+/// dashes.
+fn token_name(path: &syn::Path, cx: &Context) -> syn::Result<String> {
+    let ident = &path
+        .segments
+        .last()
+        .ok_or_else(|| syn::Error::new_spanned(path, "expected a path to a token"))?
+        .ident;
+    let name = cx
+        .visible_variables()
+        .iter()
+        .map(|variable| variable.name.clone())
+        .find(|name| token_ident(name) == *ident)
+        .unwrap_or_else(|| ident.to_string().to_lowercase().replace('_', "-"));
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse::Parser;
+
+    use super::*;
+
+    fn parse(value: &str) -> syn::Result<Value> {
+        let cx = Context::load().unwrap();
+        let parse = |input: ParseStream| Value::parse_declaration(input, &cx);
+        parse.parse_str(value)
+    }
+
+    fn css(value: &str) -> String {
+        parse(value).unwrap().to_css()
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(css("#fff"), "#fff");
+        assert_eq!(css("#000"), "#000");
+        // lexed as a float with an `a8a` suffix
+        assert_eq!(css("#1e3a8a"), "#1e3a8a");
+        assert_eq!(css("#1d4ed8"), "#1d4ed8");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(css("0 -1px"), "0 -1px");
+        assert_eq!(css(".5"), "0.5");
+        assert_eq!(css("-.5rem 50%"), "-0.5rem 50%");
+        assert_eq!(css("1 / -1"), "1 / -1");
+    }
+
+    #[test]
+    fn math() {
+        assert_eq!(css("calc($a - 1px)"), "calc(var(--a) - 1px)");
+        assert_eq!(css("calc(100% - 2rem)"), "calc(100% - 2rem)");
+        assert_eq!(css("calc(-1 * $a)"), "calc(-1 * var(--a))");
+        // outside of math functions, `-` is a sign
+        assert_eq!(css("translate(0, -1px)"), "translate(0, -1px)");
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(css("$spacing-4"), "var(--spacing-4)");
+        let value = parse("1px solid $border-color").unwrap();
+        assert_eq!(value.to_class_text(), "1px solid $border-color");
+        let names = value.variables().into_iter().map(|(name, _)| name);
+        assert_eq!(names.collect::<Vec<_>>(), ["border-color"]);
+    }
+
+    #[test]
+    fn lists_and_strings() {
+        assert_eq!(css("\"Inter\", sans-serif"), "\"Inter\", sans-serif");
+        assert_eq!(css("-webkit-box"), "-webkit-box");
+        assert_eq!(css("--custom"), "--custom");
+    }
+
+    #[test]
+    fn raw() {
+        assert_eq!(css("raw(\"2em\")"), "2em");
+        assert_eq!(css("calc(raw(\"1em\") + 2px) 0"), "calc(1em + 2px) 0");
+        assert_eq!(parse("raw(\"1e3\")").unwrap().to_class_text(), "1e3");
+        let error = parse("raw(2)").unwrap_err();
+        assert!(error.to_string().contains("expected raw CSS"), "{}", error);
+        let error = parse("raw(\"red; }\")").unwrap_err();
+        assert!(error.to_string().contains("can't contain"), "{}", error);
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(css("url(\"a.png\")"), "url(\"a.png\")");
+        let error = parse("url(a.png)").unwrap_err();
+        assert!(error.to_string().contains("must be quoted"), "{}", error);
+    }
+}
@@ -0,0 +1,48 @@
+use csm::csm;
+
+fn main() {
+    // nested blocks named after a breakpoint are scoped to a `min-width` media query
+    let classes = csm! { card,
+        padding: 1rem,
+        md: {
+            padding: 2rem,
+        },
+        lg: {
+            padding: 4rem,
+            hover: {
+                background-color: gray,
+            },
+        },
+    };
+    println!("{:?}", classes);
+    // -> "p_1rem md:p_2rem lg:p_4rem lg:hover:bg_gray"
+
+    // the default breakpoints are sm (640px), md (768px), lg (1024px) and xl (1280px). They can
+    // be replaced by adding a csm.toml file next to your Cargo.toml:
+    //
+    // [breakpoints]
+    // tablet = "768px"
+    // desktop = "80rem"
+
+    // and the CSS bundle will contain the breakpoints ordered from the smallest to the largest:
+    //
+    // .p_1rem {
+    //   padding: 1rem;
+    // }
+    //
+    // @media (width >= 768px) {
+    //   .md\:p_2rem {
+    //     padding: 2rem;
+    //   }
+    // }
+    //
+    // @media (width >= 1024px) {
+    //   .lg\:p_4rem {
+    //     padding: 4rem;
+    //   }
+    //
+    //   .lg\:hover\:bg_gray:hover {
+    //     background-color: gray;
+    //   }
+    // }
+}
@@ -0,0 +1,34 @@
+use csm::{csm, include_tokens};
+
+// tokens exported by design tools in the W3C Design Tokens format, or the Style Dictionary one,
+// can be defined as CSS variables like csm_defs! would. The path is relative to Cargo.toml,
+// and the crate gets recompiled when the file changes.
+include_tokens!(design, "examples/tokens.json");
+
+fn main() {
+    // tokens are named after their group, e.g. `color.link` becomes `$color-link`
+    let classes = csm! { link,
+        color: $color-link,
+        gap: design::SIZE_GAP,
+    };
+    println!("{:?}", classes);
+    // -> "color_$color-link gap_$size-gap"
+
+    // and get a constant in the module named after the include_tokens!, or `tokens`
+    println!("{} {}", design::COLOR_LINK, design::COLOR_LINK.value);
+    // -> var(--color-link) #3b82f6
+
+    // the CSS bundle will contain:
+    //
+    // :root {
+    //   --color-blue: #3b82f6;
+    //   --color-link: var(--color-blue);
+    //   --easing-out: cubic-bezier(0, 0, .2, 1);
+    //   --font-sans: "Source Sans Pro", sans-serif;
+    //   --shadow-card: 0 1px 3px 0 #0000001a;
+    //   --size-gap: .75rem;
+    // }
+    //
+    // composite tokens are supported when they fit in a single property, e.g. shadows, borders
+    // and transitions, but not typography.
+}
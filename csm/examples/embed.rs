@@ -0,0 +1,22 @@
+use csm::{csm, include_bundle, Bundle};
+
+fn title() -> &'static str {
+    csm! { embed_title,
+        font-size: 2rem,
+    }
+}
+
+// include_bundle! embeds the CSS bundle into the binary, so it can be served without copying
+// ./target/csm/debug/bundle.css around.
+//
+// The bundle contains the CSS of this crate and its dependencies, but only the csm! calls
+// expanded before this one, so in a real application they should live in a crate this one
+// depends on (e.g. a crate with your components). Here we rely on title() being expanded first.
+static BUNDLE: Bundle = include_bundle!();
+
+fn main() {
+    println!("<h1 class=\"{}\">hello</h1>", title());
+    println!("<style>{}</style>", BUNDLE.css);
+    // the hash changes whenever the bundle does, it can be used as an ETag
+    println!("ETag: \"{}\"", BUNDLE.hash);
+}
@@ -19,18 +19,24 @@ macro_rules! circle {
 fn main() {
     let classes = circle!(foo_id, 5rem);
     println!("{:?}", classes);
-    // -> "justify_center items_center flex_0_0_auto w_5rem d_flex rounded_9999px overflow_hidden h_5rem"
+    // -> "d_flex items_center justify_center flex_0_0_auto w_5rem h_5rem rounded_9999px overflow_hidden"
 
-    // and as a side-effect, a bundled CSS file is generated in ./target/csm/bundle.css:
+    // and as a side-effect, the CSS is written in ./target/csm/debug/css. Calling
+    // csm_build::bundle() once everything has been compiled generates a bundled CSS file in
+    // ./target/csm/debug/bundle.css:
     //
-    // .justify_center {
-    //   justify-content: center;
+    // .d_flex {
+    //   display: flex;
     // }
     //
     // .items_center {
     //   align-items: center;
     // }
     //
+    // .justify_center {
+    //   justify-content: center;
+    // }
+    //
     // .flex_0_0_auto {
     //   flex: none;
     // }
@@ -39,8 +45,8 @@ fn main() {
     //   width: 5rem;
     // }
     //
-    // .d_flex {
-    //   display: flex;
+    // .h_5rem {
+    //   height: 5rem;
     // }
     //
     // .rounded_9999px {
@@ -50,8 +56,4 @@ fn main() {
     // .overflow_hidden {
     //   overflow: hidden;
     // }
-    //
-    // .h_5rem {
-    //   height: 5rem;
-    // }
 }
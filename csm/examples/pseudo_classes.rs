@@ -0,0 +1,37 @@
+use csm::csm;
+
+fn main() {
+    // nested blocks named after a pseudo-class or pseudo-element scope their rules to it
+    let classes = csm! { link,
+        color: blue,
+        hover: {
+            color: red,
+        },
+        focus-visible: {
+            outline-style: solid,
+        },
+        before: {
+            color: black,
+        },
+    };
+    println!("{:?}", classes);
+    // -> "color_blue hover:color_red focus-visible:outline-style_solid before:color_black"
+
+    // and the CSS bundle will contain:
+    //
+    // .color_blue {
+    //   color: #00f;
+    // }
+    //
+    // .hover\:color_red:hover {
+    //   color: red;
+    // }
+    //
+    // .focus-visible\:outline-style_solid:focus-visible {
+    //   outline-style: solid;
+    // }
+    //
+    // .before\:color_black:before {
+    //   color: #000;
+    // }
+}
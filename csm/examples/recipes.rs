@@ -0,0 +1,80 @@
+use csm::recipe;
+
+// this recipe for button generates a button module, containing an enum for each variant and a
+// Variants struct holding the selected choices, and a button() function returning the classes
+// for the selected variants
+recipe! { button,
+    base: {
+        display: flex,
+    },
+    variants: {
+        visual: {
+            solid: {
+                background-color: $danger,
+                color: white,
+            },
+            outline: {
+                border-width: 1px,
+                border-color: $danger,
+            },
+        },
+        size: {
+            sm: {
+                padding: 4px,
+                font-size: 12px,
+            },
+            lg: {
+                padding: 8px,
+                font-size: 24px,
+            },
+        },
+    },
+    default_variants: {
+        visual: solid,
+        size: sm,
+    },
+    compound_variants: [
+        {
+            visual: outline,
+            size: lg,
+            css: {
+                border-width: 2px,
+            },
+        },
+    ],
+}
+
+fn main() {
+    let classes = button(button::Visual::Solid, button::Size::Lg);
+    println!("{:?}", classes);
+    // -> "d_flex bg_$danger color_white p_8px fs_24px"
+
+    let classes = button(button::Visual::Outline, button::Size::Lg);
+    println!("{:?}", classes);
+    // -> "d_flex border-width_2px border-color_$danger p_8px fs_24px"
+
+    // default_variants are used to implement Default for the enums
+    let classes = button(Default::default(), Default::default());
+    println!("{:?}", classes);
+    // -> "d_flex bg_$danger color_white p_4px fs_12px"
+
+    // variants can also be selected at runtime, e.g. from strings coming from a request
+    let variants = button::Variants {
+        size: "lg".parse().unwrap(),
+        ..Default::default()
+    };
+    println!("{:?}", variants.class());
+    // -> "d_flex bg_$danger color_white p_8px fs_24px"
+
+    // and the CSS bundle will contain every class any combination of variants can use, e.g.
+    //
+    // .d_flex {
+    //   display: flex;
+    // }
+    //
+    // .bg_\$danger {
+    //   background-color: var(--danger);
+    // }
+    //
+    // ...
+}
@@ -1,5 +1,5 @@
 use csm::csm;
-use rscx::{component, html, props, EscapeAttribute};
+use rscx::{component, html};
 
 #[tokio::main]
 async fn main() {
@@ -9,7 +9,7 @@ async fn main() {
     //
     // <!DOCTYPE html>
     // <head>
-    //   <link rel="stylesheet" href="/bundle.css" />
+    //   <link rel="stylesheet" href="/bundle.de36b0c0.css" />
     // </head>
     // <body>
     //   <main>
@@ -23,7 +23,7 @@ async fn main() {
     // </body>
     //
     //
-    // and the content of ./target/csm/bundle.css is:
+    // and the content of ./target/csm/debug/bundle.de36b0c0.css is:
     //
     // .p_2rem {
     //   padding: 2rem;
@@ -42,9 +42,10 @@ async fn app() -> String {
     html! {
         <!DOCTYPE html>
         <head>
-            <link rel="stylesheet" href="/bundle.css" />
-            // Caution! You need to manually copy ./target/csm/bundle.css to somewhere public after
-            // Rust build and serve it from your webserver!
+            <link rel="stylesheet" href={BUNDLE_HREF} />
+            // Caution! You need to copy ./target/csm/release/bundle.*.css to somewhere public after
+            // Rust build and serve it from your webserver! Since the file name changes with its
+            // content, it can be served with long-lived Cache-Control headers.
         </head>
         <body>
             <main>
@@ -80,3 +81,8 @@ async fn RedText(children: String) -> String {
         </p>
     }
 }
+
+// bundle_href! writes the bundle, with a hash of its content in the file name, and expands to its
+// URL. The bundle contains the CSS of the csm! calls expanded before this one, so in a real
+// application they should live in a crate this one depends on.
+const BUNDLE_HREF: &str = csm::bundle_href!();
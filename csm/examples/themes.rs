@@ -0,0 +1,67 @@
+use csm::{csm, csm_defs, csm_theme};
+
+fn main() {
+    // variables are defined once, with their default value
+    csm_defs! {
+        background: white,
+        text: #111,
+        accent: #d00,
+    };
+
+    // and themes give some of them another value
+    csm_theme! { dark,
+        background: #111,
+        text: #eee,
+        accent: #f66,
+    };
+    csm_theme! { sepia,
+        background: #f4ecd8,
+    };
+
+    // csm! calls don't need to know about themes, they reference the variables as usual
+    let classes = csm! { page,
+        background-color: $background,
+        color: $text,
+    };
+    println!("{:?}", classes);
+    // -> "bg_$background color_$text"
+
+    // a theme is applied by setting its name on the root element, e.g.
+    // `<html data-theme="sepia">`. Themes named `dark` or `light` also apply when no theme is
+    // set and the user's system prefers that color scheme.
+    //
+    // the CSS bundle will contain:
+    //
+    // :root {
+    //   --background: #fff;
+    //   --accent: #d00;
+    //   --text: #111;
+    // }
+    //
+    // :root[data-theme="dark"] {
+    //   --background: #111;
+    //   --accent: #f66;
+    //   --text: #eee;
+    // }
+    //
+    // @media (prefers-color-scheme: dark) {
+    //   :root:not([data-theme]) {
+    //     --background: #111;
+    //     --accent: #f66;
+    //     --text: #eee;
+    //   }
+    // }
+    //
+    // :root[data-theme="sepia"] {
+    //   --background: #f4ecd8;
+    // }
+
+    // themes generate a module of the same name too, with their values
+    println!("{} {}", dark::ACCENT, dark::ACCENT.value);
+    // -> var(--accent) #f66
+
+    // overriding a variable that no csm_defs! defines is a compile error, e.g. for
+    // `csm_theme! { dark, accnt: #f66 }`:
+    //
+    // error: undefined variable `$accnt`, did you mean `$accent`?
+}
@@ -0,0 +1,79 @@
+use csm::csm;
+
+fn main() {
+    // values can be made of numbers with or without units, negative numbers, percentages, hex
+    // colors, strings, comma-separated lists and function calls. The macro only sees Rust
+    // tokens, so the argument of `url()` must be quoted, e.g. `url("image.png")`, and CSS the
+    // Rust lexer rejects can be written as a string in `raw()`, e.g. `raw("2em")`, as `2em`
+    // looks like a number with an exponent
+    let classes = csm! { card,
+        margin: -1px 0.5rem .5rem 0,
+        line-height: 1.5,
+        font-size: raw("2em"),
+        width: calc(100% - 2rem),
+        color: #fff,
+        background-color: rgb(0 0 0 / 50%),
+        font-family: "Inter", sans-serif,
+        transition: opacity 150ms ease-in-out, transform 150ms,
+    };
+    println!("{:?}", classes);
+    // -> "margin_-1px_0.5rem_0.5rem_0 line-height_1.5 fs_2em w_calc(100%_-_2rem) color_#fff
+    //     bg_rgb(0_0_0_/_50%) font-family_\\22;Inter\\22;,_sans-serif
+    //     transition_opacity_150ms_ease-in-out,_transform_150ms"
+
+    // class names spell out the declaration: the property and each word of the value are
+    // separated by `_`, and characters that aren't valid in a CSS identifier are escaped in
+    // the bundle. Release builds use short hashed names instead, e.g. `k3x9fa`, and either
+    // scheme can be picked in csm.toml:
+    //
+    // class-names = "readable" # or "hashed"
+    //
+    // common properties are abbreviated in readable names, e.g. `w` for `width` or `bg` for
+    // `background-color`, and more abbreviations can be added, or the default ones changed:
+    //
+    // [abbreviations]
+    // margin = "m"
+    // line-height = "leading"
+
+    // declarations marked `!important` get their own class, so they don't collide with the
+    // same declaration without it
+    let classes = csm! { alert,
+        color: red !important,
+    };
+    println!("{:?}", classes);
+    // -> "color_red!"
+
+    // and the CSS bundle will contain:
+    //
+    // .margin_-1px_0\.5rem_0\.5rem_0 {
+    //   margin: -1px .5rem .5rem 0;
+    // }
+    //
+    // .line-height_1\.5 {
+    //   line-height: 1.5;
+    // }
+    //
+    // .w_calc\(100\%_-_2rem\) {
+    //   width: calc(100% - 2rem);
+    // }
+    //
+    // ...
+    //
+    // .color_red\! {
+    //   color: red !important;
+    // }
+
+    // values are checked by lightningcss, and the ones it doesn't understand are rejected, e.g.
+    // `width: 10 px`:
+    //
+    // error: value `10 px` for CSS property `width` isn't understood by lightningcss, it may be
+    // invalid (make this a warning with `check-values = "warn"`, or turn this check off with
+    // `check-values = false`)
+    //
+    // CSS it doesn't support yet, e.g. `top: anchor(--a bottom)`, can't be told apart from
+    // invalid values, so the error can be downgraded to a warning in csm.toml:
+    //
+    // check-values = "warn"
+    //
+    // or the check turned off with `check-values = false`.
+}
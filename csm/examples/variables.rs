@@ -8,6 +8,20 @@ fn main() {
 
         // dollar sign is used to reference a CSS variable
         danger: $red,
+
+        // variables accept the same values as csm!{} rules
+        spacing-4: 1rem,
+        radius-full: 9999px,
+        font-body: "Inter", sans-serif,
+        shadow-md: 0 1px 3px rgb(0 0 0 / 10%),
+    };
+
+    // csm_defs! can be given a name, so that variables can be split across several of them,
+    // possibly in different crates, as a crate can only have one unnamed csm_defs!. Defining a
+    // variable that another one already defines with a different value is a compile error.
+    csm_defs! { brand,
+        primary: #3b82f6,
+        primary-hover: #1d4ed8,
     };
 
     // then, variables can be used in normal csm!{} calls:
@@ -15,19 +29,54 @@ fn main() {
         color: $danger,
     };
     println!("{:?}", css);
-    // -> "color_danger"
+    // -> "color_$danger"
+
+    // csm_defs! also generates a `tokens` module, with a constant for each variable. They can
+    // be referenced in csm!, where a typo is caught by the compiler like any other path:
+    let css = csm! { alert,
+        border-color: tokens::DANGER,
+        padding: $spacing-4,
+        box-shadow: tokens::SHADOW_MD,
+    };
+    println!("{:?}", css);
+    // -> "border-color_$danger p_$spacing-4 box-shadow_$shadow-md"
+
+    // and used in Rust code, e.g. for inline styles or drawing on a canvas
+    println!("{} {}", tokens::DANGER, tokens::DANGER.value);
+    // -> var(--danger) #FF0000
+
+    // named csm_defs! generate a module of the same name
+    println!("{}", brand::PRIMARY_HOVER.value);
+    // -> #1d4ed8
 
     // and the CSS bundle will contain:
     //
-    // .color_danger {
+    // .color_\$danger {
     //   color: var(--danger);
     // }
     //
     // :root {
+    //   --primary: #3b82f6;
+    //   --primary-hover: #1d4ed8;
+    // }
+    //
+    // :root {
     //   --danger: var(--red);
+    //   --font-body: "Inter", sans-serif;
+    //   --radius-full: 9999px;
     //   --red: red;
+    //   --shadow-md: 0 1px 3px #0000001a;
+    //   --spacing-4: 1rem;
     // }
 
-    // note that when using $danger, it gets expanded to var(--danger), but there's no guarantee
-    // that --danger is defined (i.e. you won't get a compile time error)
+    // $danger gets expanded to var(--danger), and referencing a variable that no csm_defs! of
+    // the crate or its dependencies defines is a compile error, e.g. for `color: $dangr`:
+    //
+    // error: undefined variable `$dangr`, did you mean `$danger`?
+    //
+    // csm_defs! must be expanded before the csm! calls using its variables, so it should be in
+    // a dependency, or come first in the crate. If some variables are defined in another
+    // stylesheet, the check can be turned off in csm.toml:
+    //
+    // check-variables = false
 }
@@ -1,2 +1,59 @@
 extern crate csm_macros;
 pub use csm_macros::*;
+
+/// Error returned when parsing a choice that doesn't exist for a `recipe!` variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownChoice {
+    pub variant: &'static str,
+    pub choice: String,
+}
+
+impl std::fmt::Display for UnknownChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown choice `{}` for variant `{}`",
+            self.choice, self.variant
+        )
+    }
+}
+
+impl std::error::Error for UnknownChoice {}
+
+/// The CSS bundle embedded into the binary by [`include_bundle!`].
+///
+/// The bundle contains the CSS of the dependencies of the crate calling [`include_bundle!`],
+/// so the `csm!` calls should live in a crate it depends on: the order in which macros are
+/// expanded within a single crate is not guaranteed. The CSS of other crates, e.g. other
+/// binaries of the workspace, is left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bundle {
+    /// The bundled CSS.
+    pub css: &'static str,
+    /// A short hash of `css`, changing whenever the bundle does.
+    pub hash: &'static str,
+    /// The URL of the bundle with its hash in the file name, see [`bundle_href!`].
+    pub href: &'static str,
+}
+
+/// A CSS variable defined by [`csm_defs!`], which generates a constant for each of them in a
+/// `tokens` module. [`csm_theme!`] generates them too, with the value the theme gives them.
+///
+/// The constants can be used in `csm!` instead of `$name`, e.g. `color: tokens::DANGER`, and
+/// in Rust code, e.g. for inline styles or drawing on a canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Token {
+    /// The name of the variable, without the leading `--`.
+    pub name: &'static str,
+    /// The value of the variable, where the variables defined by the same [`csm_defs!`] are
+    /// replaced with their own value.
+    pub value: &'static str,
+    /// A reference to the variable, e.g. `var(--danger)`.
+    pub var: &'static str,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.var)
+    }
+}